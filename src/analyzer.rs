@@ -0,0 +1,666 @@
+// MIT License
+//
+// Copyright (c) 2022 Philipp Schuster <phip1611@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Decodes captured CLK/DIO signals into the commands and data of the
+//! TM1637 protocol.
+//!
+//! Capture the bus with a logic analyzer, export it with sigrok/PulseView as
+//! CSV or VCD file, load it with `parse_csv` or `parse_vcd` and pass the
+//! [`Sample`]s to a [`Decoder`]:
+//!
+//! ```rust
+//! # #[cfg(feature = "alloc")] {
+//! use tm1637_gpio_driver::analyzer::{Decoder, parse_vcd};
+//!
+//! # let vcd = "$timescale 1us $end $var wire 1 ! CLK $end $var wire 1 \" DIO $end $enddefinitions $end #0 1! 1\"";
+//! for decoded in Decoder::new().decode(parse_vcd(vcd, "CLK", "DIO").unwrap()) {
+//!     println!("{decoded}");
+//! }
+//! # }
+//! ```
+//!
+//! The decoder samples DIO at the rising edges of CLK, like the TM1637 does.
+//! The sample rate of the capture must be high enough that CLK and DIO don't
+//! change within the same sample. Parsing captures requires the `alloc`
+//! feature.
+
+use crate::keys::KeyCode;
+use crate::mappings::SegmentBits;
+use crate::{Brightness, DisplayState, ISA, render};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+
+/// Levels of CLK and DIO at a point in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sample {
+    /// Timestamp in nanoseconds.
+    pub time_ns: u64,
+    /// Whether CLK is high.
+    pub clk: bool,
+    /// Whether DIO is high.
+    pub dio: bool,
+}
+
+/// Meaning of a byte on the bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BusItem {
+    /// Data command: [`ISA::DataCommandWriteToDisplay`],
+    /// [`ISA::DataCommandFixedAddress`] or [`ISA::DataCommandReadKeys`].
+    DataCommand(ISA),
+    /// Address command that selects the display register.
+    Address(u8),
+    /// Display data.
+    Segments {
+        /// Display register that the data is written to, if the address is known.
+        register: Option<u8>,
+        /// The raw segments data.
+        byte: u8,
+        /// The character that the segments show, ignoring the point.
+        char: Option<char>,
+    },
+    /// Display control command.
+    DisplayControl {
+        /// Whether the display is on.
+        state: DisplayState,
+        /// The brightness.
+        brightness: Brightness,
+    },
+    /// Key-scan data that the TM1637 sent.
+    KeyScan {
+        /// The raw scan code.
+        scan_code: u8,
+        /// The decoded key or `None` if the scan code is invalid.
+        key: Option<KeyCode>,
+    },
+    /// A byte that doesn't make sense at this position.
+    Unknown(u8),
+}
+
+/// A decoded byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decoded {
+    /// Timestamp of the first bit in nanoseconds.
+    pub time_ns: u64,
+    /// Index of the frame, i.e. of the start condition, beginning with 0.
+    pub frame: u32,
+    /// Meaning of the byte.
+    pub item: BusItem,
+    /// Whether DIO was low during the 9th clock. For data that the driver
+    /// sends, this is the acknowledge of the TM1637.
+    pub ack: bool,
+}
+
+impl Display for Decoded {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:>12}ns frame {}: ", self.time_ns, self.frame)?;
+        match self.item {
+            BusItem::DataCommand(isa) => write!(f, "{isa:?}")?,
+            BusItem::Address(register) => write!(f, "address {register}")?,
+            BusItem::Segments {
+                register,
+                byte,
+                char,
+            } => {
+                match register {
+                    Some(register) => write!(f, "register {register} = {byte:#04x}")?,
+                    None => write!(f, "register ? = {byte:#04x}")?,
+                }
+                if let Some(c) = char {
+                    write!(f, " '{c}'")?;
+                }
+                if byte & SegmentBits::SegPoint as u8 != 0 {
+                    f.write_str(" with point")?;
+                }
+            }
+            BusItem::DisplayControl { state, brightness } => {
+                write!(f, "display {state:?}, brightness {brightness:?}")?
+            }
+            BusItem::KeyScan { scan_code, key } => match key {
+                Some(key) => write!(f, "key scan {scan_code:#04x} {key:?}")?,
+                None => write!(f, "key scan {scan_code:#04x} invalid")?,
+            },
+            BusItem::Unknown(byte) => write!(f, "unknown {byte:#04x}")?,
+        }
+        f.write_str(if self.ack { " ACK" } else { " NACK" })
+    }
+}
+
+/// Decodes [`Sample`]s of the bus into [`Decoded`] bytes.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Decoder {
+    /// The previous sample.
+    last: Option<Sample>,
+    /// Whether a frame was started and not stopped yet.
+    in_frame: bool,
+    /// Number of start conditions.
+    frames: u32,
+    /// Bits of the current byte, LSB first.
+    byte: u8,
+    /// Number of clocks of the current byte; the 9th is the ACK.
+    bits: u8,
+    /// Timestamp of the first bit of the current byte.
+    byte_time_ns: u64,
+    /// Number of decoded bytes in the current frame.
+    bytes: u16,
+    /// The first byte of the current frame.
+    command: u8,
+    /// Whether the last data command requested the key-scan data.
+    read_keys: bool,
+    /// Whether the last data command selected the fixed address mode.
+    fixed_address: bool,
+    /// The display register that is written next, if known.
+    register: Option<u8>,
+}
+
+impl Decoder {
+    /// Creates a decoder that waits for the first start condition.
+    pub const fn new() -> Self {
+        Self {
+            last: None,
+            in_frame: false,
+            frames: 0,
+            byte: 0,
+            bits: 0,
+            byte_time_ns: 0,
+            bytes: 0,
+            command: 0,
+            read_keys: false,
+            fixed_address: false,
+            register: None,
+        }
+    }
+
+    /// Decodes all samples.
+    pub fn decode<I: IntoIterator<Item = Sample>>(
+        mut self,
+        samples: I,
+    ) -> impl Iterator<Item = Decoded> {
+        samples
+            .into_iter()
+            .filter_map(move |sample| self.feed(sample))
+    }
+
+    /// Processes the next sample. Samples must be passed in chronological
+    /// order. Returns the byte that this sample completes, if any.
+    pub fn feed(&mut self, sample: Sample) -> Option<Decoded> {
+        let last = self.last.replace(sample)?;
+
+        if sample.clk && !last.clk {
+            // rising edge: DIO is sampled
+            if !self.in_frame {
+                return None;
+            }
+            if self.bits == 0 {
+                self.byte = 0;
+                self.byte_time_ns = sample.time_ns;
+            }
+            if self.bits < 8 {
+                self.byte |= u8::from(sample.dio) << self.bits;
+                self.bits += 1;
+                None
+            } else {
+                self.bits = 0;
+                Some(self.complete(!sample.dio))
+            }
+        } else if sample.clk && last.clk && sample.dio != last.dio {
+            if sample.dio {
+                // stop condition; a byte without ACK clock is not acknowledged
+                let incomplete = (self.in_frame && self.bits == 8).then(|| self.complete(false));
+                self.in_frame = false;
+                incomplete
+            } else {
+                // start condition
+                self.in_frame = true;
+                self.frames += 1;
+                self.bits = 0;
+                self.bytes = 0;
+                None
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Interprets the current byte.
+    fn complete(&mut self, ack: bool) -> Decoded {
+        let byte = self.byte;
+        self.bytes += 1;
+        let item = if self.bytes == 1 {
+            self.command = byte;
+            self.command(byte)
+        } else {
+            self.data(byte)
+        };
+        Decoded {
+            time_ns: self.byte_time_ns,
+            frame: self.frames - 1,
+            item,
+            ack,
+        }
+    }
+
+    /// Interprets the first byte of a frame.
+    fn command(&mut self, byte: u8) -> BusItem {
+        match byte & 0b1100_0000 {
+            0b0100_0000 => {
+                let isa = [
+                    ISA::DataCommandWriteToDisplay,
+                    ISA::DataCommandFixedAddress,
+                    ISA::DataCommandReadKeys,
+                ]
+                .into_iter()
+                .find(|&isa| isa as u8 == byte);
+                if let Some(isa) = isa {
+                    self.read_keys = isa == ISA::DataCommandReadKeys;
+                    self.fixed_address = isa == ISA::DataCommandFixedAddress;
+                    BusItem::DataCommand(isa)
+                } else {
+                    BusItem::Unknown(byte)
+                }
+            }
+            0b1000_0000 => BusItem::DisplayControl {
+                state: if byte & DisplayState::ON as u8 == 0 {
+                    DisplayState::OFF
+                } else {
+                    DisplayState::ON
+                },
+                brightness: Brightness::try_from(byte & 0b0000_0111)
+                    .unwrap_or_else(|_| unreachable!("brightness has 3 bits")),
+            },
+            0b1100_0000 if byte & 0b0011_1111 < 6 => {
+                self.register = Some(byte & 0b0000_0111);
+                BusItem::Address(byte & 0b0000_0111)
+            }
+            _ => BusItem::Unknown(byte),
+        }
+    }
+
+    /// Interprets a byte after the command.
+    fn data(&mut self, byte: u8) -> BusItem {
+        match self.command & 0b1100_0000 {
+            0b0100_0000 if self.read_keys && self.bytes == 2 => BusItem::KeyScan {
+                scan_code: byte,
+                key: KeyCode::from_scan_code(byte),
+            },
+            0b1100_0000 => {
+                let register = self.register;
+                if !self.fixed_address {
+                    self.register = register.map(|r| r + 1);
+                }
+                BusItem::Segments {
+                    register,
+                    byte,
+                    char: render::decode_char(byte),
+                }
+            }
+            _ => BusItem::Unknown(byte),
+        }
+    }
+}
+
+/// Errors while parsing a capture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureError {
+    /// The capture doesn't contain a channel with the given name.
+    MissingChannel,
+    /// The CSV file has neither a time column nor a samplerate comment, or
+    /// the VCD file has no valid timescale.
+    MissingTime,
+    /// The line with the given number (beginning with 1) can't be parsed.
+    InvalidLine(usize),
+}
+
+impl Display for CaptureError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::MissingChannel => f.write_str("channel not found in capture"),
+            Self::MissingTime => f.write_str("capture has no time information"),
+            Self::InvalidLine(line) => write!(f, "invalid line {line} in capture"),
+        }
+    }
+}
+
+impl core::error::Error for CaptureError {}
+
+/// Parses a CSV file exported by sigrok/PulseView.
+///
+/// Lines starting with `;` are comments. The first other line names the
+/// columns, `clk` and `dio` are the names of the channels. If there is a
+/// column whose name starts with `Time` and contains the unit in brackets
+/// (`[s]`, `[ms]`, `[us]` or `[ns]`), it provides the timestamps. Otherwise,
+/// the sample rate is taken from a comment like `; Samplerate: 1 MHz`.
+#[cfg(feature = "alloc")]
+pub fn parse_csv(csv: &str, clk: &str, dio: &str) -> Result<Vec<Sample>, CaptureError> {
+    let mut samplerate_hz = None;
+    let mut lines = csv.lines().enumerate();
+    let header = loop {
+        let Some((_, line)) = lines.next() else {
+            return Err(CaptureError::MissingChannel);
+        };
+        if let Some(comment) = line.strip_prefix(';') {
+            if let Some(rate) = comment.trim().strip_prefix("Samplerate:") {
+                samplerate_hz = parse_quantity(
+                    rate,
+                    &[("Hz", 1.0), ("kHz", 1e3), ("MHz", 1e6), ("GHz", 1e9)],
+                );
+            }
+        } else if !line.trim().is_empty() {
+            break line;
+        }
+    };
+
+    let columns = header.split(',').map(str::trim).collect::<Vec<_>>();
+    let column = |name: &str| columns.iter().position(|&c| c == name);
+    let (clk_column, dio_column) = column(clk)
+        .zip(column(dio))
+        .ok_or(CaptureError::MissingChannel)?;
+    let time = columns.iter().enumerate().find_map(|(i, c)| {
+        let unit = c
+            .strip_prefix("Time")?
+            .trim()
+            .strip_prefix('[')?
+            .strip_suffix(']')?;
+        let ns_per_unit = match unit {
+            "s" => 1e9,
+            "ms" => 1e6,
+            "us" | "µs" => 1e3,
+            "ns" => 1.0,
+            _ => return None,
+        };
+        Some((i, ns_per_unit))
+    });
+    if time.is_none() && samplerate_hz.is_none() {
+        return Err(CaptureError::MissingTime);
+    }
+
+    let mut samples = Vec::new();
+    for (index, (number, line)) in lines
+        .filter(|(_, l)| !l.trim().is_empty() && !l.starts_with(';'))
+        .enumerate()
+    {
+        let invalid = CaptureError::InvalidLine(number + 1);
+        let values = line.split(',').map(str::trim).collect::<Vec<_>>();
+        let level = |i: usize| match values.get(i) {
+            Some(&"0") => Ok(false),
+            Some(&"1") => Ok(true),
+            _ => Err(invalid),
+        };
+        let time_ns = match (time, samplerate_hz) {
+            (Some((i, ns_per_unit)), _) => values
+                .get(i)
+                .and_then(|t| t.parse::<f64>().ok())
+                .map(|t| (t * ns_per_unit + 0.5) as u64)
+                .ok_or(invalid)?,
+            (None, Some(rate)) => (index as f64 * 1e9 / rate + 0.5) as u64,
+            (None, None) => unreachable!("checked above"),
+        };
+        samples.push(Sample {
+            time_ns,
+            clk: level(clk_column)?,
+            dio: level(dio_column)?,
+        });
+    }
+    Ok(samples)
+}
+
+/// Parses a Value Change Dump (VCD) file.
+///
+/// The file may be exported by sigrok/PulseView or by
+/// [`crate::trace::Trace::to_vcd`]. `clk` and `dio` are the names of
+/// the channels. A sample is emitted for each timestamp once both channels
+/// have a known value.
+#[cfg(feature = "alloc")]
+pub fn parse_vcd(vcd: &str, clk: &str, dio: &str) -> Result<Vec<Sample>, CaptureError> {
+    let mut tokens = vcd.lines().enumerate().flat_map(|(number, line)| {
+        line.split_whitespace()
+            .map(move |token| (number + 1, token))
+    });
+
+    // declarations
+    let mut ns_per_tick = None;
+    let (mut clk_id, mut dio_id) = (None, None);
+    loop {
+        let Some((number, token)) = tokens.next() else {
+            return Err(CaptureError::MissingChannel);
+        };
+        let mut section = || {
+            let mut section = Vec::new();
+            for (_, token) in tokens.by_ref() {
+                if token == "$end" {
+                    break;
+                }
+                section.push(token);
+            }
+            section
+        };
+        match token {
+            "$timescale" => {
+                let timescale = section().concat();
+                ns_per_tick = parse_quantity(
+                    &timescale,
+                    &[
+                        ("s", 1e9),
+                        ("ms", 1e6),
+                        ("us", 1e3),
+                        ("ns", 1.0),
+                        ("ps", 1e-3),
+                        ("fs", 1e-6),
+                    ],
+                );
+                if ns_per_tick.is_none() {
+                    return Err(CaptureError::InvalidLine(number));
+                }
+            }
+            "$var" => {
+                // type, size, identifier, name
+                let var = section();
+                match var.as_slice() {
+                    [_, _, id, name, ..] if *name == clk => clk_id = Some(*id),
+                    [_, _, id, name, ..] if *name == dio => dio_id = Some(*id),
+                    [_, _, _, _, ..] => {}
+                    _ => return Err(CaptureError::InvalidLine(number)),
+                }
+            }
+            "$enddefinitions" => {
+                section();
+                break;
+            }
+            _ if token.starts_with('$') => {
+                section();
+            }
+            _ => return Err(CaptureError::InvalidLine(number)),
+        }
+    }
+    let (clk_id, dio_id) = clk_id.zip(dio_id).ok_or(CaptureError::MissingChannel)?;
+    let ns_per_tick = ns_per_tick.ok_or(CaptureError::MissingTime)?;
+
+    // value changes
+    let mut samples = Vec::new();
+    let (mut clk, mut dio) = (None, None);
+    let mut time_ns = 0;
+    let emit =
+        |samples: &mut Vec<Sample>, time_ns, clk: Option<bool>, dio: Option<bool>| match (clk, dio)
+        {
+            (Some(clk), Some(dio))
+                if samples
+                    .last()
+                    .is_none_or(|s: &Sample| (s.clk, s.dio) != (clk, dio)) =>
+            {
+                samples.push(Sample { time_ns, clk, dio });
+            }
+            _ => {}
+        };
+    while let Some((number, token)) = tokens.next() {
+        let invalid = CaptureError::InvalidLine(number);
+        if let Some(time) = token.strip_prefix('#') {
+            emit(&mut samples, time_ns, clk, dio);
+            let ticks = time.parse::<u64>().map_err(|_| invalid)?;
+            time_ns = (ticks as f64 * ns_per_tick + 0.5) as u64;
+        } else if token.starts_with('$') {
+            // $dumpvars, $dumpall, ... and their $end contain value changes
+            continue;
+        } else {
+            // scalar ("1!") or vector ("b1 !") value change
+            let (value, id) = match token.strip_prefix(['b', 'B']) {
+                Some(value) => (value, tokens.next().ok_or(invalid)?.1),
+                None => token.split_at_checked(1).ok_or(invalid)?,
+            };
+            let value = match value {
+                "1" => Some(true),
+                "0" => Some(false),
+                _ => None,
+            };
+            if id == clk_id {
+                clk = value;
+            } else if id == dio_id {
+                dio = value;
+            }
+        }
+    }
+    emit(&mut samples, time_ns, clk, dio);
+    Ok(samples)
+}
+
+/// Parses a number followed by one of the units, e.g. `1 MHz`, and returns
+/// it multiplied with the factor of the unit.
+#[cfg(feature = "alloc")]
+fn parse_quantity(text: &str, units: &[(&str, f64)]) -> Option<f64> {
+    let text = text.trim();
+    let split = text.find(|c: char| !c.is_ascii_digit() && c != '.')?;
+    let (number, unit) = text.split_at(split);
+    let factor = units.iter().find(|(u, _)| *u == unit.trim())?.1;
+    Some(number.parse::<f64>().ok()? * factor)
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::{GpioPinValue, TM1637Adapter};
+    use alloc::boxed::Box;
+    use alloc::format;
+    use alloc::string::String;
+    use core::cell::Cell;
+
+    /// Records what the adapter writes and returns the samples of the bus.
+    fn capture(write: impl Fn(&mut TM1637Adapter)) -> Vec<Sample> {
+        let adapter = TM1637Adapter::new(
+            Box::from(|_| {}),
+            Box::from(|_| {}),
+            Box::from(|| GpioPinValue::LOW),
+            Box::from(|| {}),
+        );
+        let time = Cell::new(0);
+        let (mut adapter, trace) = adapter.into_recording(move || {
+            time.set(time.get() + 1000);
+            time.get()
+        });
+        write(&mut adapter);
+        parse_vcd(&trace.to_vcd(), "clk", "dio").unwrap()
+    }
+
+    #[test]
+    fn test_decode() {
+        let samples = capture(|adapter| {
+            adapter.set_brightness(Brightness::L3);
+            adapter.write_segments_raw(&[0x3f, 0x06 | SegmentBits::SegPoint as u8], 4);
+            adapter.read_keys();
+        });
+        let items = Decoder::new()
+            .decode(samples)
+            .map(|d| (d.frame, d.item))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            [
+                (0, BusItem::DataCommand(ISA::DataCommandWriteToDisplay)),
+                (1, BusItem::Address(4)),
+                (
+                    1,
+                    BusItem::Segments {
+                        register: Some(4),
+                        byte: 0x3f,
+                        char: Some('0')
+                    }
+                ),
+                (
+                    1,
+                    BusItem::Segments {
+                        register: Some(5),
+                        byte: 0x86,
+                        char: Some('1')
+                    }
+                ),
+                (
+                    2,
+                    BusItem::DisplayControl {
+                        state: DisplayState::ON,
+                        brightness: Brightness::L3
+                    }
+                ),
+                (3, BusItem::DataCommand(ISA::DataCommandReadKeys)),
+                // the recording doesn't know what the TM1637 sent
+                (
+                    3,
+                    BusItem::KeyScan {
+                        scan_code: 0xff,
+                        key: Some(KeyCode::NoKey)
+                    }
+                ),
+            ]
+            .as_slice(),
+            items.as_slice()
+        );
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        let samples = capture(|adapter| adapter.write_display_state());
+        let mut csv = String::from("; CSV, generated by libsigrok\nTime [us],CLK,DIO\n");
+        for s in &samples {
+            csv += &format!(
+                "{},{},{}\n",
+                s.time_ns as f64 / 1000.0,
+                u8::from(s.clk),
+                u8::from(s.dio)
+            );
+        }
+        assert_eq!(Ok(samples), parse_csv(&csv, "CLK", "DIO"));
+
+        let csv = "; Samplerate: 1 MHz\nCLK,DIO\n1,1\n1,0\n0,0\n";
+        let samples = parse_csv(csv, "CLK", "DIO").unwrap();
+        assert_eq!(2000, samples[2].time_ns);
+        assert!(!samples[2].clk);
+
+        assert_eq!(
+            Err(CaptureError::MissingChannel),
+            parse_csv(csv, "SCL", "DIO")
+        );
+        assert_eq!(
+            Err(CaptureError::MissingTime),
+            parse_csv("CLK,DIO\n", "CLK", "DIO")
+        );
+        assert_eq!(
+            Err(CaptureError::InvalidLine(4)),
+            parse_csv("; Samplerate: 1 kHz\nCLK,DIO\n1,1\n1,x\n", "CLK", "DIO")
+        );
+    }
+}
@@ -0,0 +1,451 @@
+// MIT License
+//
+// Copyright (c) 2022 Philipp Schuster <phip1611@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Async counterpart of [`crate::TM1637`] for async executors.
+//!
+//! Instead of busy-waiting between clock edges, [`TM1637Async`] awaits an
+//! [`embedded_hal_async::delay::DelayNs`]. This way, other tasks can run while
+//! the bits are transmitted. The pins are regular [`embedded_hal`] pins,
+//! because setting a GPIO pin doesn't block.
+//!
+//! The frames and the retransmissions after a missing ACK are the same as in
+//! [`crate::TM1637`]. Only the basic operations are supported though: writing
+//! raw segments data, the display state and reading keys. The async driver
+//! doesn't have
+//! - a [`crate::timing::TimingProfile`]; all phases last `bit_delay_ns`,
+//! - [`crate::BusRecovery`] and `recover_bus`,
+//! - the frame buffer (`set_segments`/`flush`) and the read-back of the
+//!   segments data,
+//! - [`crate::board::BoardProfile`]s (`write_digits`),
+//!   [`crate::board::Orientation`]s, fonts and the `writer`,
+//! - the fixed address mode and `probe`.
+//!
+//! This feature must be activated in your Cargo.toml if you want to use it.
+
+use crate::keys::KeyCode;
+use crate::protocol::{self, Frame, Retransmits};
+use crate::{
+    AckPolicy, Brightness, DISPLAY_REGISTERS_COUNT, DisplayState, GpioPinValue, ISA, TM1637Error,
+};
+use embedded_hal::digital::{Error, ErrorKind, InputPin, OutputPin, PinState};
+use embedded_hal_async::delay::DelayNs;
+
+/// Async driver for the TM1637.
+///
+/// DIO must be an open-drain pin (with a pull-up resistor) that can be read
+/// while it is released, i.e. set high. A push-pull pin would drive DIO high
+/// while the TM1637 pulls it low. Errors of the pins are reported by their
+/// [`ErrorKind`] because CLK and DIO may have different error types.
+#[derive(Debug)]
+pub struct TM1637Async<CLK, DIO, DELAY> {
+    clk: CLK,
+    dio: DIO,
+    delay: DELAY,
+    bit_delay_ns: u32,
+    /// Representation of the display state in bits for the TM1637.
+    /// Bits 3-0 are for display on/off and brightness.
+    brightness: u8,
+    /// How acknowledges of the TM1637 are awaited.
+    ack_policy: AckPolicy,
+}
+
+impl<CLK, DIO, DELAY> TM1637Async<CLK, DIO, DELAY>
+where
+    CLK: OutputPin,
+    DIO: OutputPin + InputPin,
+    DELAY: DelayNs,
+{
+    /// Creates a new async driver.
+    /// Activates the display and set's the brightness to the highest value.
+    ///
+    /// * `clk` output pin connected to CLK
+    /// * `dio` open-drain pin connected to DIO
+    /// * `delay` async delay provider
+    /// * `bit_delay_ns` delay after a bit has been written to a pin. The TM1637
+    ///   supports clock frequencies up to 250kHz, i.e. 2000ns are fine.
+    pub fn new(
+        mut clk: CLK,
+        mut dio: DIO,
+        delay: DELAY,
+        bit_delay_ns: u32,
+    ) -> Result<Self, TM1637Error<ErrorKind>> {
+        clk.set_low().map_err(|e| TM1637Error::Pin(e.kind()))?;
+        dio.set_low().map_err(|e| TM1637Error::Pin(e.kind()))?;
+        Ok(Self {
+            clk,
+            dio,
+            delay,
+            bit_delay_ns,
+            brightness: DisplayState::ON as u8 | Brightness::L7 as u8,
+            ack_policy: AckPolicy::default(),
+        })
+    }
+
+    /// Destroys the driver and returns the pins and the delay provider.
+    pub fn release(self) -> (CLK, DIO, DELAY) {
+        (self.clk, self.dio, self.delay)
+    }
+
+    /// Sets the display state. The display state is the 3rd bit of the
+    /// "display control"-command.
+    /// This setting is not committed until a write operation has been made.
+    pub const fn set_display_state(&mut self, ds: DisplayState) {
+        self.brightness = ds as u8 | (self.brightness & 0b0000_0111);
+    }
+
+    /// Sets the brightness of the screen. The brightness are the lower
+    /// 3 bits of the "display control"-command.
+    /// This setting is not committed until a write operation has been made.
+    pub const fn set_brightness(&mut self, brightness: Brightness) {
+        self.brightness = (self.brightness & DisplayState::ON as u8) | brightness as u8;
+    }
+
+    /// Sets how acknowledges of the TM1637 are awaited and whether frames
+    /// are retransmitted if the TM1637 doesn't acknowledge them.
+    pub const fn set_ack_policy(&mut self, ack_policy: AckPolicy) {
+        self.ack_policy = ack_policy;
+    }
+
+    /// Writes all raw segments data beginning at the position into the display
+    /// registers and commits the display state afterwards.
+    /// See [`crate::TM1637::try_write_segments_raw`].
+    pub async fn write_segments_raw(
+        &mut self,
+        segments: &[u8],
+        pos: u8,
+    ) -> Result<(), TM1637Error<ErrorKind>> {
+        if segments.is_empty() {
+            return Ok(());
+        }
+        if !protocol::registers_fit(pos, segments.len()) {
+            return Err(TM1637Error::InvalidPosition);
+        }
+
+        self.send_frame(&Frame::new(&[ISA::DataCommandWriteToDisplay as u8]))
+            .await?;
+        self.send_frame(&Frame::segments(pos, segments)).await?;
+
+        self.write_display_state().await
+    }
+
+    /// Send command that sets the display state on the micro controller.
+    pub async fn write_display_state(&mut self) -> Result<(), TM1637Error<ErrorKind>> {
+        self.send_frame(&Frame::display_control(self.brightness))
+            .await?;
+        Ok(())
+    }
+
+    /// Clears the display.
+    pub async fn clear(&mut self) -> Result<(), TM1637Error<ErrorKind>> {
+        self.write_segments_raw(&[0; DISPLAY_REGISTERS_COUNT], 0)
+            .await
+    }
+
+    /// Reads the key-scan data from the TM1637 and decodes it.
+    /// See [`crate::TM1637::try_read_keys`].
+    pub async fn read_keys(&mut self) -> Result<KeyCode, TM1637Error<ErrorKind>> {
+        // the frame always reads a byte
+        let scan_code = self
+            .send_frame(&Frame::READ_KEYS)
+            .await?
+            .unwrap_or_default();
+        KeyCode::from_scan_code(scan_code).ok_or(TM1637Error::InvalidScanCode(scan_code))
+    }
+
+    /// Transmits a frame and retransmits it according to the [`AckPolicy`].
+    /// Returns the byte that the TM1637 sent if the frame reads one.
+    async fn send_frame(&mut self, frame: &Frame) -> Result<Option<u8>, TM1637Error<ErrorKind>> {
+        let mut retransmits = Retransmits::new(&self.ack_policy);
+        loop {
+            let res = self.transmit_frame(frame).await;
+            if !retransmits.retry(&res) {
+                return res;
+            }
+        }
+    }
+
+    /// Transmits a frame once, enclosed by a start and a stop condition.
+    async fn transmit_frame(
+        &mut self,
+        frame: &Frame,
+    ) -> Result<Option<u8>, TM1637Error<ErrorKind>> {
+        self.start().await?;
+        let mut res = Ok(None);
+        for &byte in frame.bytes() {
+            res = self.write_byte_and_wait_ack(byte).await.map(|()| None);
+            if res.is_err() {
+                break;
+            }
+        }
+        if res.is_ok() && frame.reads_byte() {
+            res = self.read_byte_and_wait_ack().await.map(Some);
+        }
+        // always finish the frame, even on errors, so that the bus is idle again
+        self.stop().await?;
+        res
+    }
+
+    /// Writes a byte bit by bit (LSB first) and waits for the acknowledge.
+    async fn write_byte_and_wait_ack(&mut self, byte: u8) -> Result<(), TM1637Error<ErrorKind>> {
+        let mut data = byte;
+        for _ in 0..8 {
+            self.write_clk(GpioPinValue::LOW)?;
+            self.write_dio(GpioPinValue::from(data & 0x01))?;
+            self.bit_delay().await;
+            self.write_clk(GpioPinValue::HIGH)?;
+            self.bit_delay().await;
+            data >>= 1;
+        }
+
+        if self.recv_ack().await? {
+            Ok(())
+        } else {
+            Err(TM1637Error::NoAck(byte))
+        }
+    }
+
+    /// Reads a byte bit by bit (LSB first) and waits for the acknowledge.
+    async fn read_byte_and_wait_ack(&mut self) -> Result<u8, TM1637Error<ErrorKind>> {
+        let mut data = 0;
+        for i in 0..8 {
+            self.write_clk(GpioPinValue::LOW)?;
+            // release DIO so that the TM1637 can pull it low
+            self.release_dio()?;
+            self.bit_delay().await;
+            self.write_clk(GpioPinValue::HIGH)?;
+            self.bit_delay().await;
+            data |= (self.read_dio()? as u8) << i;
+        }
+
+        // ACK cycle; we don't insist on an acknowledge for data that the
+        // TM1637 sent itself
+        self.recv_ack().await?;
+        Ok(data)
+    }
+
+    /// Start condition: DIO goes from high to low while CLK is high.
+    async fn start(&mut self) -> Result<(), TM1637Error<ErrorKind>> {
+        self.write_dio(GpioPinValue::HIGH)?;
+        self.write_clk(GpioPinValue::HIGH)?;
+        self.bit_delay().await;
+        self.write_dio(GpioPinValue::LOW)?;
+        self.bit_delay().await;
+        Ok(())
+    }
+
+    /// Stop condition: DIO goes from low to high while CLK is high.
+    async fn stop(&mut self) -> Result<(), TM1637Error<ErrorKind>> {
+        self.write_dio(GpioPinValue::LOW)?;
+        self.write_clk(GpioPinValue::HIGH)?;
+        self.bit_delay().await;
+        self.write_dio(GpioPinValue::HIGH)?;
+        self.bit_delay().await;
+        Ok(())
+    }
+
+    /// Receives one acknowledgment after a byte was sent. Returns whether
+    /// the TM1637 acknowledged the byte. See [`AckPolicy::timeout`].
+    async fn recv_ack(&mut self) -> Result<bool, TM1637Error<ErrorKind>> {
+        self.write_clk(GpioPinValue::LOW)?;
+        self.release_dio()?;
+        self.bit_delay().await;
+        self.write_clk(GpioPinValue::HIGH)?;
+
+        let mut ack = false;
+        for _ in 0..self.ack_policy.samples() {
+            ack = matches!(self.read_dio()?, GpioPinValue::LOW);
+            // also after the ACK: CLK must stay high for at least one bit delay
+            self.bit_delay().await;
+            if ack {
+                break;
+            }
+        }
+
+        self.write_clk(GpioPinValue::LOW)?;
+        self.write_dio(GpioPinValue::LOW)?;
+        self.bit_delay().await;
+
+        Ok(ack)
+    }
+
+    fn write_clk(&mut self, value: GpioPinValue) -> Result<(), TM1637Error<ErrorKind>> {
+        self.clk
+            .set_state(PinState::from(value))
+            .map_err(|e| TM1637Error::Pin(e.kind()))
+    }
+
+    fn write_dio(&mut self, value: GpioPinValue) -> Result<(), TM1637Error<ErrorKind>> {
+        self.dio
+            .set_state(PinState::from(value))
+            .map_err(|e| TM1637Error::Pin(e.kind()))
+    }
+
+    /// Releases DIO so that the TM1637 can drive it. DIO is open-drain, i.e.
+    /// high means released.
+    fn release_dio(&mut self) -> Result<(), TM1637Error<ErrorKind>> {
+        self.dio.set_high().map_err(|e| TM1637Error::Pin(e.kind()))
+    }
+
+    fn read_dio(&mut self) -> Result<GpioPinValue, TM1637Error<ErrorKind>> {
+        match self.dio.is_high() {
+            Ok(true) => Ok(GpioPinValue::HIGH),
+            Ok(false) => Ok(GpioPinValue::LOW),
+            Err(e) => Err(TM1637Error::Pin(e.kind())),
+        }
+    }
+
+    async fn bit_delay(&mut self) {
+        self.delay.delay_ns(self.bit_delay_ns).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::rc::Rc;
+    use alloc::vec::Vec;
+    use core::cell::RefCell;
+    use core::convert::Infallible;
+    use embedded_hal::digital::ErrorType;
+    use futures::executor::block_on;
+
+    /// Shared state of the mock bus.
+    #[derive(Default)]
+    struct Bus {
+        clk: bool,
+        /// DIO bits sampled at rising CLK edges.
+        bits: Vec<bool>,
+        dio: bool,
+        /// Whether the mock TM1637 pulls DIO low when it is read.
+        acking: bool,
+        delays: usize,
+    }
+
+    struct MockClk(Rc<RefCell<Bus>>);
+    struct MockDio(Rc<RefCell<Bus>>);
+    struct MockDelay(Rc<RefCell<Bus>>);
+
+    impl ErrorType for MockClk {
+        type Error = Infallible;
+    }
+
+    impl OutputPin for MockClk {
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            self.0.borrow_mut().clk = false;
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            let mut bus = self.0.borrow_mut();
+            if !bus.clk {
+                let dio = bus.dio;
+                bus.bits.push(dio);
+            }
+            bus.clk = true;
+            Ok(())
+        }
+    }
+
+    impl ErrorType for MockDio {
+        type Error = Infallible;
+    }
+
+    impl OutputPin for MockDio {
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            self.0.borrow_mut().dio = false;
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            self.0.borrow_mut().dio = true;
+            Ok(())
+        }
+    }
+
+    impl InputPin for MockDio {
+        fn is_high(&mut self) -> Result<bool, Infallible> {
+            Ok(!self.0.borrow().acking)
+        }
+
+        fn is_low(&mut self) -> Result<bool, Infallible> {
+            Ok(self.0.borrow().acking)
+        }
+    }
+
+    // the mocks share their state via Rc; they are never sent to other threads
+    #[allow(clippy::future_not_send)]
+    impl DelayNs for MockDelay {
+        async fn delay_ns(&mut self, _ns: u32) {
+            self.0.borrow_mut().delays += 1;
+        }
+    }
+
+    fn setup(acking: bool) -> (TM1637Async<MockClk, MockDio, MockDelay>, Rc<RefCell<Bus>>) {
+        let bus = Rc::new(RefCell::new(Bus {
+            acking,
+            ..Bus::default()
+        }));
+        let display = TM1637Async::new(
+            MockClk(bus.clone()),
+            MockDio(bus.clone()),
+            MockDelay(bus.clone()),
+            1000,
+        )
+        .unwrap();
+        (display, bus)
+    }
+
+    #[test]
+    fn test_write_segments_raw() {
+        let (mut display, bus) = setup(true);
+        block_on(display.write_segments_raw(&[0x3f], 0)).unwrap();
+
+        let bus = bus.borrow();
+        assert!(bus.delays > 0);
+        // every byte is followed by the ACK clock: 9 clocks per byte; the
+        // first start condition and every stop condition have one rising edge
+        let byte = |frame_bits: &[bool]| {
+            frame_bits[..8]
+                .iter()
+                .enumerate()
+                .fold(0_u8, |acc, (i, &bit)| acc | (u8::from(bit) << i))
+        };
+        // start, 0x40, stop, start, 0xc0, 0x3f, stop, start, 0x8f, stop
+        assert_eq!(0x40, byte(&bus.bits[1..]));
+        assert_eq!(0xc0, byte(&bus.bits[1 + 9 + 1..]));
+        assert_eq!(0x3f, byte(&bus.bits[1 + 9 + 1 + 9..]));
+        assert_eq!(0x8f, byte(&bus.bits[1 + 9 + 1 + 9 + 9 + 1..]));
+    }
+
+    #[test]
+    fn test_no_ack() {
+        let (mut display, _) = setup(false);
+        assert_eq!(
+            Err(TM1637Error::NoAck(0x8f)),
+            block_on(display.write_display_state())
+        );
+        assert_eq!(
+            Err(TM1637Error::InvalidPosition),
+            block_on(display.write_segments_raw(&[0; 2], 5))
+        );
+    }
+}
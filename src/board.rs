@@ -0,0 +1,292 @@
+// MIT License
+//
+// Copyright (c) 2022 Philipp Schuster <phip1611@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Board profiles describe how the digits of a display module are wired to
+//! the grids (display registers) of the TM1637.
+//!
+//! Many 4-digit modules wire digit `i` (from left to right) to grid `i`. Common
+//! 6-digit modules however wire their digits in the order 2, 1, 0, 5, 4, 3.
+//! With the right [`BoardProfile`], [`crate::TM1637::write_digits`] renders text
+//! and numbers from left to right on both of them.
+//!
+//! The profile also tells whether the point bit ([`crate::mappings::SegmentBits::SegPoint`])
+//! of each digit is a decimal point or whether a single point bit lights a
+//! colon, see [`PointStyle`].
+
+use crate::DISPLAY_REGISTERS_COUNT;
+use crate::mappings::SegmentBits;
+
+/// What the point bit of the digits lights on a display module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointStyle {
+    /// Each digit has its own decimal point.
+    DecimalPoint,
+    /// Only the point bit of the contained digit is wired: it lights the
+    /// colon after this digit, e.g. after digit 1 on the 4-digit modules of
+    /// AZ-Delivery.
+    Colon(u8),
+}
+
+/// Maps the logical digits of a display module (from left to right) to the
+/// physical grids of the TM1637.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoardProfile {
+    /// Grid of each digit. Only the first `digits` entries are valid.
+    grids: [u8; DISPLAY_REGISTERS_COUNT],
+    /// Number of digits of the display module.
+    digits: u8,
+    /// What the point bits light.
+    point_style: PointStyle,
+}
+
+impl BoardProfile {
+    /// Digit `i` is wired to grid `i`; all 6 grids are used. This is the default.
+    pub const LINEAR: Self = Self {
+        grids: [0, 1, 2, 3, 4, 5],
+        digits: 6,
+        point_style: PointStyle::DecimalPoint,
+    };
+
+    /// 4-digit modules, like the one from AZ-Delivery, where digit `i` is
+    /// wired to grid `i` and the point bit of digit 1 lights the colon.
+    pub const FOUR_DIGIT: Self = Self {
+        grids: [0, 1, 2, 3, 0, 0],
+        digits: 4,
+        point_style: PointStyle::Colon(1),
+    };
+
+    /// Common 6-digit modules whose digits are wired to the grids 2, 1, 0, 5, 4, 3.
+    pub const SIX_DIGIT: Self = Self {
+        grids: [2, 1, 0, 5, 4, 3],
+        digits: 6,
+        point_style: PointStyle::DecimalPoint,
+    };
+
+    /// Creates a profile from the grids of the digits from left to right.
+    /// Each digit has a decimal point, see [`BoardProfile::with_point_style`].
+    /// Returns `None` if there are more than [`DISPLAY_REGISTERS_COUNT`] digits,
+    /// if a grid is invalid or if a grid is used twice.
+    pub const fn new(grids: &[u8]) -> Option<Self> {
+        if grids.len() > DISPLAY_REGISTERS_COUNT {
+            return None;
+        }
+        let mut profile = Self {
+            grids: [0; DISPLAY_REGISTERS_COUNT],
+            digits: grids.len() as u8,
+            point_style: PointStyle::DecimalPoint,
+        };
+        let mut used = 0_u8;
+        let mut i = 0;
+        while i < grids.len() {
+            let grid = grids[i];
+            if grid as usize >= DISPLAY_REGISTERS_COUNT || used & (1 << grid) != 0 {
+                return None;
+            }
+            used |= 1 << grid;
+            profile.grids[i] = grid;
+            i += 1;
+        }
+        Some(profile)
+    }
+
+    /// Returns the profile with the given point style.
+    pub const fn with_point_style(mut self, point_style: PointStyle) -> Self {
+        self.point_style = point_style;
+        self
+    }
+
+    /// Returns what the point bits light.
+    pub const fn point_style(&self) -> PointStyle {
+        self.point_style
+    }
+
+    /// Returns the number of digits of the display module.
+    pub const fn digits(&self) -> usize {
+        self.digits as usize
+    }
+
+    /// Returns the grid that the digit is wired to or `None` if the display
+    /// module doesn't have this digit.
+    pub const fn grid(&self, digit: usize) -> Option<u8> {
+        if digit < self.digits() {
+            Some(self.grids[digit])
+        } else {
+            None
+        }
+    }
+
+    /// Returns the digit that is wired to the grid or `None` if no digit of
+    /// the display module is wired to it.
+    pub const fn digit(&self, grid: u8) -> Option<usize> {
+        let mut digit = 0;
+        while digit < self.digits() {
+            if self.grids[digit] == grid {
+                return Some(digit);
+            }
+            digit += 1;
+        }
+        None
+    }
+}
+
+impl Default for BoardProfile {
+    fn default() -> Self {
+        Self::LINEAR
+    }
+}
+
+/// How the display module is mounted. The driver transforms the segments
+/// data on the way to the TM1637, so that text still reads correctly.
+///
+/// Only the digits of the [`BoardProfile`] are transformed. On modules with a
+/// [`PointStyle::DecimalPoint`], a rotated or mirrored point would be in front
+/// of its digit, so it is moved to the following digit instead. A colon looks
+/// the same in all orientations and stays where it is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Orientation {
+    /// Rotate the segments of each digit by 180°.
+    pub rotate_segments: bool,
+    /// Show the digits in reverse order.
+    pub reverse_digits: bool,
+    /// Mirror the segments of each digit horizontally.
+    pub mirror_segments: bool,
+}
+
+impl Orientation {
+    /// The display module is mounted as intended. This is the default.
+    pub const NORMAL: Self = Self {
+        rotate_segments: false,
+        reverse_digits: false,
+        mirror_segments: false,
+    };
+
+    /// The display module is mounted upside down.
+    pub const UPSIDE_DOWN: Self = Self {
+        rotate_segments: true,
+        reverse_digits: true,
+        mirror_segments: false,
+    };
+
+    /// The display module is seen via a mirror, e.g. in a head-up display.
+    pub const MIRRORED: Self = Self {
+        rotate_segments: false,
+        reverse_digits: true,
+        mirror_segments: true,
+    };
+
+    /// Returns the segments of one digit rotated and/or mirrored as
+    /// configured. The point bit is left as it is.
+    pub const fn transform_segments(&self, segments: u8) -> u8 {
+        let mut segments = segments;
+        if self.rotate_segments {
+            // A <-> D, B <-> E, C <-> F
+            segments = (segments & 0b1100_0000)
+                | ((segments & 0b0000_0111) << 3)
+                | ((segments & 0b0011_1000) >> 3);
+        }
+        if self.mirror_segments {
+            segments = swap_bits(segments, SegmentBits::SegB, SegmentBits::SegF);
+            segments = swap_bits(segments, SegmentBits::SegC, SegmentBits::SegE);
+        }
+        segments
+    }
+
+    /// Whether the point of a digit ends up in front of the digit, i.e.
+    /// whether exactly one of rotation and mirroring is configured.
+    pub(crate) const fn moves_points(&self) -> bool {
+        self.rotate_segments != self.mirror_segments
+    }
+}
+
+/// Swaps the two segments in `segments`.
+const fn swap_bits(segments: u8, a: SegmentBits, b: SegmentBits) -> u8 {
+    let (a, b) = (a as u8, b as u8);
+    let mut swapped = segments & !(a | b);
+    if segments & a != 0 {
+        swapped |= b;
+    }
+    if segments & b != 0 {
+        swapped |= a;
+    }
+    swapped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new() {
+        assert_eq!(
+            Some(BoardProfile::SIX_DIGIT),
+            BoardProfile::new(&[2, 1, 0, 5, 4, 3])
+        );
+        assert_eq!(
+            Some(BoardProfile::FOUR_DIGIT),
+            BoardProfile::new(&[0, 1, 2, 3]).map(|p| p.with_point_style(PointStyle::Colon(1)))
+        );
+        assert_eq!(None, BoardProfile::new(&[0, 1, 1]));
+        assert_eq!(None, BoardProfile::new(&[6]));
+        assert_eq!(None, BoardProfile::new(&[0; 7]));
+
+        assert_eq!(Some(3), BoardProfile::FOUR_DIGIT.grid(3));
+        assert_eq!(None, BoardProfile::FOUR_DIGIT.grid(4));
+        assert_eq!(Some(2), BoardProfile::SIX_DIGIT.digit(0));
+        assert_eq!(None, BoardProfile::FOUR_DIGIT.digit(4));
+    }
+
+    #[test]
+    fn test_transform_segments() {
+        use crate::encoding::encode_char;
+        let point = SegmentBits::SegPoint as u8;
+
+        let upside_down = Orientation::UPSIDE_DOWN;
+        assert_eq!(
+            encode_char('1'),
+            upside_down.transform_segments(encode_char('I'))
+        );
+        assert_eq!(
+            encode_char('7'),
+            upside_down.transform_segments(encode_char('L'))
+        );
+        assert_eq!(
+            encode_char('d') | point,
+            upside_down.transform_segments(encode_char('P') | point)
+        );
+        for segments in 0..=u8::MAX {
+            let rotated = upside_down.transform_segments(segments);
+            assert_eq!(segments, upside_down.transform_segments(rotated));
+        }
+
+        let mirrored = Orientation::MIRRORED;
+        assert_eq!(encode_char('J'), mirrored.transform_segments(0x3C));
+        assert_eq!(
+            encode_char('d'),
+            mirrored.transform_segments(encode_char('b'))
+        );
+        assert_eq!(
+            encode_char('8'),
+            mirrored.transform_segments(encode_char('8'))
+        );
+        assert_eq!(0x42, Orientation::NORMAL.transform_segments(0x42));
+    }
+}
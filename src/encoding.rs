@@ -0,0 +1,673 @@
+// MIT License
+//
+// Copyright (c) 2022 Philipp Schuster <phip1611@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Functions to encode numbers, chars and strings into the bit representation
+//! of the 7-segment display.
+//!
+//! None of them allocates memory, except `encode_string` and
+//! `encode_string_with_font` which require the `alloc` feature.
+
+use crate::NumberOverflowError;
+use crate::board::PointStyle;
+use crate::font::{AsciiFont, Font};
+use crate::mappings::{LoCharBits, NumCharBits, SegmentBits, SpecialCharBits, UpCharBits};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// Encodes a number from 0 to 9999 on the display.
+/// Greater numbers wrap around; use [`encode_integer`] to detect overflows.
+pub fn encode_number(num: u16) -> [u8; 4] {
+    let mut num = num % 10000;
+    let mut bits: [u8; 4] = [0; 4];
+    for i in 0..4 {
+        let digit = (num % 10) as u8;
+        bits[3 - i] = encode_digit(digit);
+        num /= 10;
+    }
+    bits
+}
+
+/// Encodes a number/digit from 0 to 9 to it's bit representation on the display.
+/// This is not the char (ASCII) representation. It's a number/integer.
+pub const fn encode_digit(digit: u8) -> u8 {
+    let digit = digit % 10;
+    if digit == 0 {
+        NumCharBits::Zero as u8
+    } else if digit == 1 {
+        NumCharBits::One as u8
+    } else if digit == 2 {
+        NumCharBits::Two as u8
+    } else if digit == 3 {
+        NumCharBits::Three as u8
+    } else if digit == 4 {
+        NumCharBits::Four as u8
+    } else if digit == 5 {
+        NumCharBits::Five as u8
+    } else if digit == 6 {
+        NumCharBits::Six as u8
+    } else if digit == 7 {
+        NumCharBits::Seven as u8
+    } else if digit == 8 {
+        NumCharBits::Eight as u8
+    }
+    // else if digit == 9 { NumCharBits::Nine as u8 }
+    else {
+        NumCharBits::Nine as u8
+    }
+}
+
+/// Encodes a hexadecimal digit from 0 to 15 to its bit representation on the
+/// display, using the glyphs `A`, `b`, `C`, `d`, `E` and `F` for 10 to 15.
+pub const fn encode_hex_digit(digit: u8) -> u8 {
+    match digit % 16 {
+        10 => UpCharBits::UpA as u8,
+        11 => LoCharBits::LoB as u8,
+        12 => UpCharBits::UpC as u8,
+        13 => LoCharBits::LoD as u8,
+        14 => UpCharBits::UpE as u8,
+        15 => UpCharBits::UpF as u8,
+        digit => encode_digit(digit),
+    }
+}
+
+/// The base in which [`encode_integer`] shows a number.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Radix {
+    /// Base 2.
+    Binary = 2,
+    /// Base 8.
+    Octal = 8,
+    /// Base 10.
+    Decimal = 10,
+    /// Base 16 with the digits `A`, `b`, `C`, `d`, `E` and `F`.
+    Hexadecimal = 16,
+}
+
+/// How [`encode_integer`] fills the digits in front of a number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Padding {
+    /// Leave them blank. The minus sign is directly in front of the number.
+    #[default]
+    Blank,
+    /// Fill them with zeros. The minus sign is in the first digit.
+    Zeros,
+}
+
+/// Encodes an integer right-aligned into `N` digits, e.g. `N = 4` or `N = 6`
+/// for common display modules. Negative numbers get a leading minus sign.
+///
+/// Fails if the number (and its sign) has more than `N` digits.
+pub fn encode_integer<const N: usize>(
+    num: i64,
+    radix: Radix,
+    padding: Padding,
+) -> Result<[u8; N], NumberOverflowError> {
+    let mut bits = [0; N];
+    encode_integer_into(num, radix, padding, &mut bits)?;
+    Ok(bits)
+}
+
+/// Encodes an integer right-aligned into all digits of `buf`. See
+/// [`encode_integer`].
+///
+/// Fails if the number (and its sign) has more digits than `buf`. `buf` is
+/// unchanged then.
+pub fn encode_integer_into(
+    num: i64,
+    radix: Radix,
+    padding: Padding,
+    buf: &mut [u8],
+) -> Result<(), NumberOverflowError> {
+    let radix = u64::from(radix as u8);
+    let negative = num < 0;
+    let magnitude = num.unsigned_abs();
+
+    let mut digits = 1;
+    let mut rest = magnitude / radix;
+    while rest > 0 {
+        digits += 1;
+        rest /= radix;
+    }
+    if digits + usize::from(negative) > buf.len() {
+        return Err(NumberOverflowError);
+    }
+
+    let (padding_bits, number_bits) = buf.split_at_mut(buf.len() - digits);
+    let mut rest = magnitude;
+    for bits in number_bits.iter_mut().rev() {
+        *bits = encode_hex_digit((rest % radix) as u8);
+        rest /= radix;
+    }
+    match padding {
+        Padding::Blank => padding_bits.fill(SpecialCharBits::Space as u8),
+        Padding::Zeros => padding_bits.fill(encode_digit(0)),
+    }
+    if negative {
+        let sign = match padding {
+            Padding::Blank => padding_bits.len() - 1,
+            Padding::Zeros => 0,
+        };
+        padding_bits[sign] = SpecialCharBits::Minus as u8;
+    }
+    Ok(())
+}
+
+/// Encodes a fixed-point number, i.e. `value / 10^scale`, right-aligned
+/// into `N` digits. See [`encode_fixed_point_into`].
+///
+/// Fails if the integer part (and the sign) doesn't fit. Use e.g.
+/// `unwrap_or([SpecialCharBits::Minus as u8; N])` to show `----` then.
+pub fn encode_fixed_point<const N: usize>(
+    value: i64,
+    scale: u32,
+) -> Result<[u8; N], NumberOverflowError> {
+    let mut bits = [0; N];
+    encode_fixed_point_into(value, scale, &[], &mut bits)?;
+    Ok(bits)
+}
+
+/// Encodes a fixed-point number, i.e. `value / 10^scale`, right-aligned
+/// into all digits of `buf`.
+///
+/// As many decimals as fit are shown; the decimal point is the point bit of
+/// the last digit of the integer part. The value is rounded half away from
+/// zero, e.g. `(2345, 2)` (23.45) becomes `23.5` on 3 digits and `(-5, 1)`
+/// (-0.5) becomes `-0.5`.
+///
+/// Fails if the integer part (and the sign) doesn't fit. `buf` starts with
+/// the `overflow` glyphs then, e.g. `----` or `Err`, and is blank otherwise.
+pub fn encode_fixed_point_into(
+    value: i64,
+    scale: u32,
+    overflow: &[u8],
+    buf: &mut [u8],
+) -> Result<(), NumberOverflowError> {
+    let negative = value < 0;
+    let magnitude = u128::from(value.unsigned_abs());
+    let max_decimals = scale.min(u32::try_from(buf.len()).unwrap_or(u32::MAX));
+    for decimals in (0..=max_decimals).rev() {
+        // too large divisors round everything to 0
+        let rounded = 10_u128
+            .checked_pow(scale - decimals)
+            .map_or(0, |divisor| (magnitude + divisor / 2) / divisor);
+        if encode_decimals_into(negative, rounded, decimals as usize, buf) {
+            return Ok(());
+        }
+    }
+    encode_overflow_into(overflow, buf);
+    Err(NumberOverflowError)
+}
+
+/// Encodes a floating-point number right-aligned into `N` digits. See
+/// [`encode_float_into`].
+///
+/// Fails if the integer part (and the sign) doesn't fit or if the value is
+/// not finite.
+pub fn encode_float<const N: usize>(value: f32) -> Result<[u8; N], NumberOverflowError> {
+    let mut bits = [0; N];
+    encode_float_into(value, &[], &mut bits)?;
+    Ok(bits)
+}
+
+/// Encodes a floating-point number right-aligned into all digits of `buf`.
+///
+/// As many decimals as fit are shown, like with [`encode_fixed_point_into`].
+/// For example, 23.456 becomes `23.46` on 4 digits and `23.4560` on 6 digits,
+/// i.e. every digit is used and the decimals are padded with zeros.
+///
+/// Fails if the integer part (and the sign) doesn't fit or if the value is
+/// not finite. `buf` starts with the `overflow` glyphs then.
+pub fn encode_float_into(
+    value: f32,
+    overflow: &[u8],
+    buf: &mut [u8],
+) -> Result<(), NumberOverflowError> {
+    if value.is_finite() {
+        let negative = value < 0.0;
+        let magnitude = f64::from(if negative { -value } else { value });
+        for decimals in (0..=buf.len()).rev() {
+            let factor = (0..decimals).fold(1.0, |factor, _| factor * 10.0);
+            // saturates for huge values; they don't fit anyway
+            let rounded = (magnitude * factor + 0.5) as u128;
+            if encode_decimals_into(negative, rounded, decimals, buf) {
+                return Ok(());
+            }
+        }
+    }
+    encode_overflow_into(overflow, buf);
+    Err(NumberOverflowError)
+}
+
+/// Encodes `magnitude / 10^decimals` right-aligned into `buf`. Returns
+/// `false` and leaves `buf` unchanged if it doesn't fit.
+fn encode_decimals_into(negative: bool, magnitude: u128, decimals: usize, buf: &mut [u8]) -> bool {
+    // no "-0.0"
+    let negative = negative && magnitude > 0;
+    let mut digits = 1;
+    let mut rest = magnitude / 10;
+    while rest > 0 {
+        digits += 1;
+        rest /= 10;
+    }
+    // at least one digit in front of the decimal point
+    let digits = digits.max(decimals + 1);
+    if digits + usize::from(negative) > buf.len() {
+        return false;
+    }
+
+    let (padding_bits, number_bits) = buf.split_at_mut(buf.len() - digits);
+    let mut rest = magnitude;
+    for bits in number_bits.iter_mut().rev() {
+        *bits = encode_digit((rest % 10) as u8);
+        rest /= 10;
+    }
+    if decimals > 0 {
+        number_bits[digits - decimals - 1] |= SegmentBits::SegPoint as u8;
+    }
+    padding_bits.fill(SpecialCharBits::Space as u8);
+    if negative {
+        padding_bits[padding_bits.len() - 1] = SpecialCharBits::Minus as u8;
+    }
+    true
+}
+
+/// Shows the overflow glyphs at the beginning of `buf`.
+fn encode_overflow_into(overflow: &[u8], buf: &mut [u8]) {
+    buf.fill(SpecialCharBits::Space as u8);
+    let len = overflow.len().min(buf.len());
+    buf[..len].copy_from_slice(&overflow[..len]);
+}
+
+/// Encodes a char for the 7-segment display.
+///
+/// Uses [`AsciiFont::DEFAULT`], which has a glyph for every printable ASCII
+/// character and the degree sign. Since there is no representation for every
+/// char in each case (lower, upper) there will be an replacement for lowercase
+/// chars by their uppercase counterpart and vice versa. Unknown chars will be
+/// a zero byte (space).
+///
+/// This function doesn't know about a [`crate::font::GlyphRegistry`]. To
+/// embed icons in strings, register them in a registry and use the
+/// `*_with_font`-functions or set it as the font of the driver with
+/// [`crate::TM1637::with_font`].
+pub const fn encode_char(c: char) -> u8 {
+    match AsciiFont::DEFAULT.get(c) {
+        Some(bits) => bits,
+        None => SpecialCharBits::Space as u8,
+    }
+}
+
+/// Encodes a char for the 7-segment display with the given font. Chars
+/// without a glyph will be a zero byte (space).
+pub fn encode_char_with_font(c: char, font: &(impl Font + ?Sized)) -> u8 {
+    font.glyph(c).unwrap_or(SpecialCharBits::Space as u8)
+}
+
+/// Encodes a string for the 7-segment display. This uses
+/// `encode_char` for each character.
+#[cfg(feature = "alloc")]
+pub fn encode_string(str: &str) -> Vec<u8> {
+    str.chars().map(encode_char).collect()
+}
+
+/// Encodes a string for the 7-segment display into the given buffer without
+/// allocating memory. This uses [`encode_char`] for each character. Characters
+/// that don't fit into the buffer are skipped.
+///
+/// Returns the number of bytes that were written into `buf`.
+pub fn encode_string_into(str: &str, buf: &mut [u8]) -> usize {
+    buf.iter_mut()
+        .zip(encode_string_iter(str))
+        .map(|(dst, bits)| *dst = bits)
+        .count()
+}
+
+/// Returns an iterator that lazily encodes a string for the 7-segment
+/// display. This uses [`encode_char`] for each character.
+pub fn encode_string_iter(str: &str) -> impl Iterator<Item = u8> + '_ {
+    str.chars().map(encode_char)
+}
+
+/// Encodes a string for the 7-segment display with the given font. This
+/// uses [`encode_char_with_font`] for each character.
+#[cfg(feature = "alloc")]
+pub fn encode_string_with_font(str: &str, font: &(impl Font + ?Sized)) -> Vec<u8> {
+    encode_string_iter_with_font(str, font).collect()
+}
+
+/// Like [`encode_string_into`] but with the given font.
+pub fn encode_string_into_with_font(
+    str: &str,
+    font: &(impl Font + ?Sized),
+    buf: &mut [u8],
+) -> usize {
+    buf.iter_mut()
+        .zip(encode_string_iter_with_font(str, font))
+        .map(|(dst, bits)| *dst = bits)
+        .count()
+}
+
+/// Like [`encode_string_iter`] but with the given font.
+pub fn encode_string_iter_with_font<'a>(
+    str: &'a str,
+    font: &'a (impl Font + ?Sized),
+) -> impl Iterator<Item = u8> + 'a {
+    str.chars().map(|c| encode_char_with_font(c, font))
+}
+
+/// Encodes a string like [`encode_string`] but folds each `'.'` and `':'`
+/// into the point bit of the preceding character. See
+/// [`encode_string_iter_with_points`].
+#[cfg(feature = "alloc")]
+pub fn encode_string_with_points(str: &str, point_style: PointStyle) -> Vec<u8> {
+    encode_string_iter_with_points(str, point_style).collect()
+}
+
+/// Like [`encode_string_into`] but folds each `'.'` and `':'` into the point
+/// bit of the preceding character. See [`encode_string_iter_with_points`].
+pub fn encode_string_with_points_into(str: &str, point_style: PointStyle, buf: &mut [u8]) -> usize {
+    buf.iter_mut()
+        .zip(encode_string_iter_with_points(str, point_style))
+        .map(|(dst, bits)| *dst = bits)
+        .count()
+}
+
+/// Returns an iterator that lazily encodes a string with folded points.
+///
+/// Like [`encode_string_iter`] but each `'.'` and `':'` is folded into the
+/// point bit ([`SegmentBits::SegPoint`]) of the preceding character. This
+/// way, `"12.34"` and `"1.2.3.4"` occupy four digits.
+///
+/// With [`PointStyle::DecimalPoint`], a point that can't be folded (at the
+/// beginning or after another point) occupies a digit of its own. With
+/// [`PointStyle::Colon`], only the point after the colon digit is shown; all
+/// others are dropped. The first character is digit 0.
+pub fn encode_string_iter_with_points(
+    str: &str,
+    point_style: PointStyle,
+) -> impl Iterator<Item = u8> + '_ {
+    encode_string_iter_with_points_and_font(str, point_style, &AsciiFont::DEFAULT)
+}
+
+/// Like [`encode_string_iter_with_points`] but with the given font.
+pub fn encode_string_iter_with_points_and_font<'a>(
+    str: &'a str,
+    point_style: PointStyle,
+    font: &'a (impl Font + ?Sized),
+) -> impl Iterator<Item = u8> + 'a {
+    let mut chars = str.chars().peekable();
+    let mut digit = 0;
+    core::iter::from_fn(move || {
+        loop {
+            let c = chars.next()?;
+            let bits = if is_point(c) {
+                match point_style {
+                    PointStyle::DecimalPoint => SegmentBits::SegPoint as u8,
+                    // the colon can't be shown at this digit
+                    PointStyle::Colon(_) => continue,
+                }
+            } else {
+                let shows_point = match point_style {
+                    PointStyle::DecimalPoint => true,
+                    PointStyle::Colon(colon_digit) => digit == usize::from(colon_digit),
+                };
+                let point = chars.next_if(|&c| is_point(c)).is_some() && shows_point;
+                encode_char_with_font(c, font)
+                    | if point {
+                        SegmentBits::SegPoint as u8
+                    } else {
+                        0
+                    }
+            };
+            digit += 1;
+            return Some(bits);
+        }
+    })
+}
+
+/// Returns whether the char is shown with the point bit of a digit.
+const fn is_point(c: char) -> bool {
+    matches!(c, '.' | ':')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_number() {
+        let f = encode_digit;
+        assert_eq!([f(1), f(2), f(3), f(4)], encode_number(1234));
+        assert_eq!([f(9), f(9), f(9), f(9)], encode_number(9999));
+        assert_eq!([f(0), f(0), f(0), f(0)], encode_number(10000));
+        assert_eq!([f(7), f(6), f(5), f(4)], encode_number(7654));
+    }
+
+    #[test]
+    fn test_encode_string_into() {
+        let mut buf = [0; 4];
+        assert_eq!(2, encode_string_into("Hi", &mut buf));
+        assert_eq!([encode_char('H'), encode_char('i'), 0, 0], buf);
+        assert_eq!(4, encode_string_into("Hello", &mut buf));
+        assert_eq!(encode_char('l'), buf[3]);
+    }
+
+    #[test]
+    fn test_encode_with_font() {
+        // the glyphs of the mappings are unchanged
+        assert_eq!(UpCharBits::UpA as u8, encode_char('A'));
+        assert_eq!(LoCharBits::LoB as u8, encode_char('B'));
+        assert_eq!(UpCharBits::UpC as u8, encode_char('c'));
+        assert_eq!(SpecialCharBits::Space as u8, encode_char('\t'));
+
+        let digits_only = |c: char| c.to_digit(10).map(|d| encode_digit(d as u8));
+        let mut buf = [0xff; 3];
+        assert_eq!(
+            3,
+            encode_string_into_with_font("1a2", &digits_only, &mut buf)
+        );
+        assert_eq!([encode_digit(1), 0, encode_digit(2)], buf);
+
+        let font = AsciiFont::DEFAULT.with_glyph('K', 0x75);
+        assert!(encode_string_iter_with_font("OK", &font).eq([UpCharBits::UpO as u8, 0x75]));
+    }
+
+    #[test]
+    fn test_encode_string_with_points() {
+        let p = SegmentBits::SegPoint as u8;
+        let f = encode_digit;
+        let mut buf = [0; 4];
+        assert_eq!(
+            4,
+            encode_string_with_points_into("12.34", PointStyle::DecimalPoint, &mut buf)
+        );
+        assert_eq!([f(1), f(2) | p, f(3), f(4)], buf);
+        assert_eq!(
+            4,
+            encode_string_with_points_into("1.2.3.4.", PointStyle::DecimalPoint, &mut buf)
+        );
+        assert_eq!([f(1) | p, f(2) | p, f(3) | p, f(4) | p], buf);
+        // points that can't be folded
+        assert!(
+            encode_string_iter_with_points(".5..", PointStyle::DecimalPoint).eq([p, f(5) | p, p])
+        );
+
+        // only the colon after digit 1 is shown
+        assert!(
+            encode_string_iter_with_points("1.2:34.", PointStyle::Colon(1)).eq([
+                f(1),
+                f(2) | p,
+                f(3),
+                f(4)
+            ])
+        );
+    }
+
+    #[test]
+    fn test_encode_integer() {
+        let (f, blank, minus) = (encode_digit, 0, SpecialCharBits::Minus as u8);
+        assert_eq!(
+            Ok([blank, blank, f(4), f(2)]),
+            encode_integer(42, Radix::Decimal, Padding::Blank)
+        );
+        assert_eq!(
+            Ok([blank, minus, f(4), f(2)]),
+            encode_integer(-42, Radix::Decimal, Padding::Blank)
+        );
+        assert_eq!(
+            Ok([minus, f(0), f(4), f(2)]),
+            encode_integer(-42, Radix::Decimal, Padding::Zeros)
+        );
+        assert_eq!(
+            Ok([f(0); 4]),
+            encode_integer(0, Radix::Decimal, Padding::Zeros)
+        );
+        assert_eq!(
+            Ok([blank, blank, f(1), f(0), f(0), f(0), f(0), f(0)]),
+            encode_integer(0o100_000, Radix::Octal, Padding::Blank)
+        );
+        assert_eq!(
+            Ok([f(1), f(0), f(1), f(1)]),
+            encode_integer(0b1011, Radix::Binary, Padding::Blank)
+        );
+        assert_eq!(
+            Ok([
+                blank,
+                UpCharBits::UpC as u8,
+                UpCharBits::UpA as u8,
+                UpCharBits::UpF as u8,
+                UpCharBits::UpE as u8,
+                f(0),
+            ]),
+            encode_integer(0xCAFE0, Radix::Hexadecimal, Padding::Blank)
+        );
+        assert_eq!(LoCharBits::LoB as u8, encode_hex_digit(11));
+        assert_eq!(LoCharBits::LoD as u8, encode_hex_digit(13));
+
+        // overflows
+        assert_eq!(
+            Err(NumberOverflowError),
+            encode_integer::<4>(10_000, Radix::Decimal, Padding::Blank)
+        );
+        assert_eq!(
+            Err(NumberOverflowError),
+            encode_integer::<4>(-1000, Radix::Decimal, Padding::Zeros)
+        );
+        assert_eq!(
+            Err(NumberOverflowError),
+            encode_integer::<4>(16, Radix::Binary, Padding::Blank)
+        );
+        let mut buf = [0xff; 6];
+        assert_eq!(
+            Err(NumberOverflowError),
+            encode_integer_into(i64::MIN, Radix::Hexadecimal, Padding::Blank, &mut buf)
+        );
+        assert_eq!([0xff; 6], buf);
+    }
+
+    #[test]
+    fn test_encode_fixed_point() {
+        let (f, p, minus) = (
+            encode_digit,
+            SegmentBits::SegPoint as u8,
+            SpecialCharBits::Minus as u8,
+        );
+        const ERR: [u8; 3] = [encode_char('E'), encode_char('r'), encode_char('r')];
+        let mut buf = [0; 4];
+
+        assert_eq!(Ok(()), encode_fixed_point_into(235, 1, &ERR, &mut buf));
+        assert_eq!([0, f(2), f(3) | p, f(5)], buf);
+        // as many decimals as fit
+        assert_eq!(Ok(()), encode_fixed_point_into(2_345, 3, &ERR, &mut buf));
+        assert_eq!([f(2) | p, f(3), f(4), f(5)], buf);
+        // rounded
+        assert_eq!(Ok(()), encode_fixed_point_into(123_456, 2, &ERR, &mut buf));
+        assert_eq!([f(1), f(2), f(3), f(5)], buf);
+        assert_eq!(Ok(()), encode_fixed_point_into(99_996, 3, &ERR, &mut buf));
+        assert_eq!([f(1), f(0), f(0) | p, f(0)], buf);
+        assert_eq!(Ok(()), encode_fixed_point_into(-5, 1, &ERR, &mut buf));
+        assert_eq!([0, minus, f(0) | p, f(5)], buf);
+        assert_eq!(Ok(()), encode_fixed_point_into(-4, 3, &ERR, &mut buf));
+        // rounded to 0, without sign
+        assert_eq!([0, f(0) | p, f(0), f(0)], buf);
+        assert_eq!(Ok(()), encode_fixed_point_into(-4, 4, &ERR, &mut buf));
+        assert_eq!([f(0) | p, f(0), f(0), f(0)], buf);
+        assert_eq!(Ok(()), encode_fixed_point_into(1, 50, &ERR, &mut buf));
+        assert_eq!([f(0) | p, f(0), f(0), f(0)], buf);
+
+        // overflows
+        assert_eq!(
+            Err(NumberOverflowError),
+            encode_fixed_point_into(99_995, 1, &ERR, &mut buf)
+        );
+        assert_eq!([ERR[0], ERR[1], ERR[2], 0], buf);
+        assert_eq!(
+            Err(NumberOverflowError),
+            encode_fixed_point_into(-1_000, 0, &[minus; 4], &mut buf)
+        );
+        assert_eq!([minus; 4], buf);
+
+        assert_eq!(
+            Ok([0, f(2), f(3) | p, f(5)]),
+            encode_fixed_point::<4>(235, 1)
+        );
+        assert_eq!(Ok([f(2), f(3) | p, f(5)]), encode_fixed_point::<3>(2345, 2));
+        assert_eq!(Err(NumberOverflowError), encode_fixed_point::<2>(-235, 1));
+    }
+
+    #[test]
+    fn test_encode_float() {
+        let (f, p, minus) = (
+            encode_digit,
+            SegmentBits::SegPoint as u8,
+            SpecialCharBits::Minus as u8,
+        );
+        let overflow = [minus; 4];
+        let mut buf = [0; 4];
+
+        assert_eq!(Ok(()), encode_float_into(23.456, &overflow, &mut buf));
+        assert_eq!([f(2), f(3) | p, f(4), f(6)], buf);
+        assert_eq!(Ok(()), encode_float_into(-1.25, &overflow, &mut buf));
+        assert_eq!([minus, f(1) | p, f(2), f(5)], buf);
+        assert_eq!(Ok(()), encode_float_into(9999.4, &overflow, &mut buf));
+        assert_eq!([f(9); 4], buf);
+        assert_eq!(Ok(()), encode_float_into(-0.0001, &overflow, &mut buf));
+        assert_eq!([f(0) | p, f(0), f(0), f(0)], buf);
+
+        let mut buf = [0; 6];
+        assert_eq!(Ok(()), encode_float_into(23.456, &overflow, &mut buf));
+        assert_eq!([f(2), f(3) | p, f(4), f(5), f(6), f(0)], buf);
+
+        let mut buf = [0; 4];
+        for value in [9999.5, -1000.0, f32::NAN, f32::INFINITY] {
+            assert_eq!(
+                Err(NumberOverflowError),
+                encode_float_into(value, &overflow, &mut buf)
+            );
+            assert_eq!(overflow, buf);
+        }
+
+        assert_eq!(Ok([f(2), f(3) | p, f(5)]), encode_float::<3>(23.46));
+        assert_eq!(Err(NumberOverflowError), encode_float::<2>(-100.0));
+    }
+}
@@ -0,0 +1,96 @@
+// MIT License
+//
+// Copyright (c) 2022 Philipp Schuster <phip1611@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Error type of the fallible API of the TM1637 driver.
+
+use core::fmt::{Debug, Display, Formatter};
+
+/// Errors that can occur while talking to the TM1637.
+///
+/// `E` is the error type of the underlying GPIO interface/library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TM1637Error<E> {
+    /// The TM1637 did not acknowledge the contained byte. Probably no display
+    /// is connected or the wiring is broken.
+    NoAck(u8),
+    /// Accessing a GPIO pin failed.
+    Pin(E),
+    /// A GPIO pin is already in use by someone else, e.g. the kernel.
+    Busy,
+    /// The requested display register position is out of range.
+    InvalidPosition,
+    /// The TM1637 reported a key-scan code that doesn't describe a key.
+    /// This indicates a transmission error.
+    InvalidScanCode(u8),
+}
+
+impl<E: Display> Display for TM1637Error<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NoAck(byte) => write!(f, "TM1637 did not acknowledge byte {byte:#04x}"),
+            Self::Pin(e) => write!(f, "GPIO pin error: {e}"),
+            Self::Busy => write!(f, "GPIO pin is already in use"),
+            Self::InvalidPosition => write!(f, "display register position out of range"),
+            Self::InvalidScanCode(code) => write!(f, "invalid key-scan code {code:#04x}"),
+        }
+    }
+}
+
+impl<E: Debug + Display> core::error::Error for TM1637Error<E> {}
+
+/// The value can't be converted because it doesn't describe a valid setting,
+/// e.g. a [`crate::Brightness`] greater than 7. Contains the invalid value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidValueError(pub u8);
+
+impl Display for InvalidValueError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "invalid value {:#04x}", self.0)
+    }
+}
+
+impl core::error::Error for InvalidValueError {}
+
+/// A [`crate::font::GlyphRegistry`] has no room for another glyph. Contains
+/// the char that couldn't be registered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GlyphRegistryFullError(pub char);
+
+impl Display for GlyphRegistryFullError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "no room in the glyph registry for {:?}", self.0)
+    }
+}
+
+impl core::error::Error for GlyphRegistryFullError {}
+
+/// A number doesn't fit into the given number of digits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberOverflowError;
+
+impl Display for NumberOverflowError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "number doesn't fit into the digits")
+    }
+}
+
+impl core::error::Error for NumberOverflowError {}
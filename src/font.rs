@@ -0,0 +1,331 @@
+// MIT License
+//
+// Copyright (c) 2022 Philipp Schuster <phip1611@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Fonts for the 7-segment display, i.e. mappings from characters to segments.
+//!
+//! [`AsciiFont::DEFAULT`] has a glyph for every printable ASCII character.
+//! Many of them can only be approximated on seven segments, e.g. `K` looks
+//! like `H` and `M`/`W` are drawn with two strokes. Implement [`Font`] (or
+//! pass a closure) to use your own glyphs with the `*_with_font`-functions in
+//! [`crate::encoding`] or set it as the font of the driver with
+//! [`crate::TM1637::with_font`].
+//!
+//! A [`GlyphRegistry`] binds additional chars, e.g. from the Unicode private
+//! use area, to icons and looks them up before another font:
+//!
+//! ```
+//! use tm1637_gpio_driver::encoding::encode_string_into_with_font;
+//! use tm1637_gpio_driver::font::GlyphRegistry;
+//! use tm1637_gpio_driver::mappings::SegmentBits;
+//!
+//! const BATTERY: char = '\u{E000}';
+//! static GLYPHS: GlyphRegistry<4> = GlyphRegistry::new().with(
+//!     BATTERY,
+//!     SegmentBits::SegA as u8 | SegmentBits::SegD as u8 | SegmentBits::SegG as u8,
+//! );
+//!
+//! let mut buf = [0; 4];
+//! encode_string_into_with_font("\u{E000}80", &GLYPHS, &mut buf);
+//! assert_eq!(0b0100_1001, buf[0]);
+//! ```
+
+use crate::GlyphRegistryFullError;
+use crate::mappings::{LoCharBits, NumCharBits, SegmentBits, SpecialCharBits, UpCharBits};
+
+/// Maps characters to their bit representation on the 7-segment display.
+pub trait Font {
+    /// Returns the segments of the glyph for `c` or `None` if the font has
+    /// no glyph for it.
+    fn glyph(&self, c: char) -> Option<u8>;
+}
+
+impl<F: Fn(char) -> Option<u8>> Font for F {
+    fn glyph(&self, c: char) -> Option<u8> {
+        self(c)
+    }
+}
+
+/// Number of printable ASCII characters, from `' '` to `'~'`.
+pub const ASCII_GLYPHS_COUNT: usize = 95;
+
+/// Font with one glyph for each printable ASCII character plus the degree
+/// sign (`°`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AsciiFont {
+    /// Glyphs of the characters `' '` to `'~'`.
+    table: [u8; ASCII_GLYPHS_COUNT],
+}
+
+impl AsciiFont {
+    /// The default font. The glyphs of the characters that have a mapping in
+    /// [`crate::mappings`] are taken from there.
+    #[rustfmt::skip]
+    pub const DEFAULT: Self = Self::new([
+        // ' ', '!', '"', '#', '$', '%', '&', '''
+        SpecialCharBits::Space as u8, 0x82, 0x22, 0x7E, UpCharBits::UpS as u8, 0xD2, 0x46, 0x02,
+        // '(', ')', '*', '+', ',', '-', '.', '/'
+        0x39, 0x0F, 0x49, 0x70, 0x04, SpecialCharBits::Minus as u8, SpecialCharBits::Dot as u8, 0x52,
+        // '0' - '9'
+        NumCharBits::Zero as u8, NumCharBits::One as u8, NumCharBits::Two as u8,
+        NumCharBits::Three as u8, NumCharBits::Four as u8, NumCharBits::Five as u8,
+        NumCharBits::Six as u8, NumCharBits::Seven as u8, NumCharBits::Eight as u8,
+        NumCharBits::Nine as u8,
+        // ':', ';', '<', '=', '>', '?', '@'
+        0x09, 0x0D, 0x61, SpecialCharBits::Equals as u8, 0x43, SpecialCharBits::QuestionMark as u8, 0x7B,
+        // 'A' - 'G'
+        UpCharBits::UpA as u8, LoCharBits::LoB as u8, UpCharBits::UpC as u8, LoCharBits::LoD as u8,
+        UpCharBits::UpE as u8, UpCharBits::UpF as u8, UpCharBits::UpG as u8,
+        // 'H' - 'N'; K looks like H, M is drawn with two strokes
+        UpCharBits::UpH as u8, UpCharBits::UpI as u8, UpCharBits::UpJ as u8, UpCharBits::UpH as u8,
+        UpCharBits::UpL as u8, 0x15, LoCharBits::LoN as u8,
+        // 'O' - 'U'
+        UpCharBits::UpO as u8, UpCharBits::UpP as u8, LoCharBits::LoQ as u8, LoCharBits::LoR as u8,
+        UpCharBits::UpS as u8, LoCharBits::LoT as u8, UpCharBits::UpU as u8,
+        // 'V' - 'Z'; W is drawn with two strokes, X looks like H, Z like 2
+        UpCharBits::UpU as u8, 0x2A, UpCharBits::UpH as u8, LoCharBits::LoY as u8, NumCharBits::Two as u8,
+        // '[', '\', ']', '^', '_', '`'
+        0x39, 0x64, 0x0F, 0x23, SpecialCharBits::Underscore as u8, 0x20,
+        // 'a' - 'g'
+        LoCharBits::LoA as u8, LoCharBits::LoB as u8, UpCharBits::UpC as u8, LoCharBits::LoD as u8,
+        UpCharBits::UpE as u8, UpCharBits::UpF as u8, UpCharBits::UpG as u8,
+        // 'h' - 'n'
+        LoCharBits::LoH as u8, UpCharBits::UpI as u8, UpCharBits::UpJ as u8, UpCharBits::UpH as u8,
+        UpCharBits::UpL as u8, 0x15, LoCharBits::LoN as u8,
+        // 'o' - 'u'
+        LoCharBits::LoO as u8, UpCharBits::UpP as u8, LoCharBits::LoQ as u8, LoCharBits::LoR as u8,
+        UpCharBits::UpS as u8, LoCharBits::LoT as u8, LoCharBits::LoU as u8,
+        // 'v' - 'z'
+        LoCharBits::LoU as u8, 0x2A, UpCharBits::UpH as u8, LoCharBits::LoY as u8, NumCharBits::Two as u8,
+        // '{', '|', '}', '~'
+        0x39, UpCharBits::UpI as u8, 0x0F, SegmentBits::SegA as u8,
+    ]);
+
+    /// Creates a font from the glyphs of the characters `' '` to `'~'`.
+    pub const fn new(table: [u8; ASCII_GLYPHS_COUNT]) -> Self {
+        Self { table }
+    }
+
+    /// Returns the font with another glyph for `c`. Characters that are not
+    /// printable ASCII characters are ignored.
+    pub const fn with_glyph(mut self, c: char, segments: u8) -> Self {
+        if let Some(index) = Self::index(c) {
+            self.table[index] = segments;
+        }
+        self
+    }
+
+    /// Returns the glyph for `c`. Like [`Font::glyph`] but usable in `const`
+    /// contexts.
+    pub const fn get(&self, c: char) -> Option<u8> {
+        match Self::index(c) {
+            Some(index) => Some(self.table[index]),
+            None if c == '°' => Some(SpecialCharBits::Degree as u8),
+            None => None,
+        }
+    }
+
+    /// Returns the index of `c` in the table.
+    const fn index(c: char) -> Option<usize> {
+        match c {
+            ' '..='~' => Some(c as usize - ' ' as usize),
+            _ => None,
+        }
+    }
+}
+
+impl Default for AsciiFont {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl Font for AsciiFont {
+    fn glyph(&self, c: char) -> Option<u8> {
+        self.get(c)
+    }
+}
+
+/// Custom glyphs for up to `N` chars that are looked up before the glyphs of
+/// the fallback font `F`.
+///
+/// Glyphs can be registered at compile time with [`GlyphRegistry::with`] or
+/// at runtime with [`GlyphRegistry::insert`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GlyphRegistry<const N: usize, F = AsciiFont> {
+    /// The registered chars and their glyphs. Only the first `len` are used.
+    glyphs: [(char, u8); N],
+    len: usize,
+    fallback: F,
+}
+
+impl<const N: usize> GlyphRegistry<N> {
+    /// Creates an empty registry with [`AsciiFont::DEFAULT`] as fallback.
+    pub const fn new() -> Self {
+        Self::with_fallback(AsciiFont::DEFAULT)
+    }
+}
+
+impl<const N: usize> Default for GlyphRegistry<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize, F> GlyphRegistry<N, F> {
+    /// Creates an empty registry with the given fallback font.
+    pub const fn with_fallback(fallback: F) -> Self {
+        Self {
+            glyphs: [('\0', 0); N],
+            len: 0,
+            fallback,
+        }
+    }
+
+    /// Returns the registry with the glyph for `c` registered. Replaces the
+    /// glyph if `c` is already registered. This is meant for `const`/`static`
+    /// registries.
+    ///
+    /// # Panics
+    /// Panics if the registry is full. In `const` contexts, this is a
+    /// compile-time error.
+    pub const fn with(mut self, c: char, segments: u8) -> Self {
+        match self.try_insert(c, segments) {
+            Ok(_) => self,
+            Err(_) => panic!("the glyph registry is full"),
+        }
+    }
+
+    /// Registers the glyph for `c` and returns the glyph that was registered
+    /// for `c` before.
+    pub const fn insert(
+        &mut self,
+        c: char,
+        segments: u8,
+    ) -> Result<Option<u8>, GlyphRegistryFullError> {
+        self.try_insert(c, segments)
+    }
+
+    /// Removes the glyph for `c` and returns it.
+    pub fn remove(&mut self, c: char) -> Option<u8> {
+        let index = self.index(c)?;
+        let segments = self.glyphs[index].1;
+        self.glyphs.copy_within(index + 1..self.len, index);
+        self.len -= 1;
+        Some(segments)
+    }
+
+    /// Returns the registered glyph for `c`, without the fallback font.
+    pub const fn get(&self, c: char) -> Option<u8> {
+        match self.index(c) {
+            Some(index) => Some(self.glyphs[index].1),
+            None => None,
+        }
+    }
+
+    /// Returns the fallback font.
+    pub const fn fallback(&self) -> &F {
+        &self.fallback
+    }
+
+    /// Registers the glyph; shared by [`Self::with`] and [`Self::insert`].
+    const fn try_insert(
+        &mut self,
+        c: char,
+        segments: u8,
+    ) -> Result<Option<u8>, GlyphRegistryFullError> {
+        if let Some(index) = self.index(c) {
+            let old = self.glyphs[index].1;
+            self.glyphs[index].1 = segments;
+            return Ok(Some(old));
+        }
+        if self.len == N {
+            return Err(GlyphRegistryFullError(c));
+        }
+        self.glyphs[self.len] = (c, segments);
+        self.len += 1;
+        Ok(None)
+    }
+
+    /// Returns the index of `c` in `glyphs`.
+    const fn index(&self, c: char) -> Option<usize> {
+        let mut i = 0;
+        while i < self.len {
+            if self.glyphs[i].0 == c {
+                return Some(i);
+            }
+            i += 1;
+        }
+        None
+    }
+}
+
+impl<const N: usize, F: Font> Font for GlyphRegistry<N, F> {
+    fn glyph(&self, c: char) -> Option<u8> {
+        self.get(c).or_else(|| self.fallback.glyph(c))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ascii_font() {
+        let font = AsciiFont::DEFAULT;
+        assert_eq!(Some(NumCharBits::Seven as u8), font.get('7'));
+        assert_eq!(Some(0x76), font.get('K'));
+        assert_eq!(Some(0x15), font.get('m'));
+        assert_eq!(Some(SpecialCharBits::Degree as u8), font.get('°'));
+        assert_eq!(None, font.get('\n'));
+        assert_eq!(None, font.get('ä'));
+        // every printable character but the space is visible
+        assert!(('!'..='~').all(|c| font.get(c).is_some_and(|bits| bits != 0)));
+
+        let font = font.with_glyph('K', 0x75).with_glyph('\n', 0xff);
+        assert_eq!(Some(0x75), font.glyph('K'));
+        assert_eq!(None, font.glyph('\n'));
+    }
+
+    #[test]
+    fn test_glyph_registry() {
+        const ARROW_UP: char = '\u{E001}';
+        const GLYPHS: GlyphRegistry<2> = GlyphRegistry::new().with(ARROW_UP, 0x23).with('K', 0x75);
+        assert_eq!(Some(0x23), GLYPHS.glyph(ARROW_UP));
+        // registered glyphs are preferred ...
+        assert_eq!(Some(0x75), GLYPHS.glyph('K'));
+        // ... to those of the fallback
+        assert_eq!(AsciiFont::DEFAULT.get('A'), GLYPHS.glyph('A'));
+        assert_eq!(None, GLYPHS.glyph('\u{E002}'));
+
+        let mut glyphs = GLYPHS;
+        assert_eq!(
+            Err(GlyphRegistryFullError('\u{E002}')),
+            glyphs.insert('\u{E002}', 1)
+        );
+        assert_eq!(Some(0x23), glyphs.remove(ARROW_UP));
+        assert_eq!(None, glyphs.remove(ARROW_UP));
+        assert_eq!(Ok(None), glyphs.insert('\u{E002}', 1));
+        assert_eq!(Ok(Some(0x75)), glyphs.insert('K', 0x76));
+        assert_eq!(Some(1), glyphs.get('\u{E002}'));
+        assert_eq!(Some(0x76), glyphs.get('K'));
+    }
+}
@@ -27,20 +27,31 @@
 /// We have 4 displays so we can display 4 digits.
 pub const DISPLAY_COUNT: usize = 4;
 
+use crate::board::{BoardProfile, PointStyle};
+use crate::encoding::{encode_string_iter_with_points_and_font, encode_string_with_font};
+use crate::font::Font;
 use crate::mappings::SegmentBits;
 use crate::{Brightness, DisplayState, TM1637Adapter};
 use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::Infallible;
 
 /// Displays a text over and over again. The text will move "animated" across the
 /// screen from right to left.
 /// Blocks the calling thread because this is an infinite loop.
-pub fn display_text_banner_in_loop(adapter: &mut TM1637Adapter, text: &str, sleep_fn: &dyn Fn()) {
+///
+/// Dots are replaced by spaces. They are only folded into the preceding digit
+/// if a [`BoardProfile`] with decimal points was set on the adapter; the
+/// default profile is treated as [`BoardProfile::FOUR_DIGIT`].
+pub fn display_text_banner_in_loop<F: Font>(
+    adapter: &mut TM1637Adapter<Infallible, F>,
+    text: &str,
+    sleep_fn: &dyn Fn(),
+) {
     adapter.set_display_state(DisplayState::ON);
     adapter.set_brightness(Brightness::L7);
 
-    // remove dots because this display only has one double point which looks weird.
-    let data = text.replace('.', " ");
-    let data = TM1637Adapter::encode_string(&data);
+    let data = banner_data(adapter.board_profile(), adapter.font(), text);
 
     // +1 because the upper border in a range is exclusive
     // otherwise last char is lost!
@@ -56,6 +67,23 @@ pub fn display_text_banner_in_loop(adapter: &mut TM1637Adapter, text: &str, slee
     }
 }
 
+/// Encodes the text of [`display_text_banner_in_loop`] for the board.
+fn banner_data(board_profile: BoardProfile, font: &dyn Font, text: &str) -> Vec<u8> {
+    // this module is made for the 4-digit module with the colon
+    let board_profile = if board_profile == BoardProfile::default() {
+        BoardProfile::FOUR_DIGIT
+    } else {
+        board_profile
+    };
+    match board_profile.point_style() {
+        PointStyle::DecimalPoint => {
+            encode_string_iter_with_points_and_font(text, PointStyle::DecimalPoint, font).collect()
+        }
+        // remove dots because this display only has one double point which looks weird.
+        PointStyle::Colon(_) => encode_string_with_font(&text.replace('.', " "), font),
+    }
+}
+
 /// Displays "hh:mm" with blinking double point on the display.
 /// Blocks the calling thread because this is an infinite loop.
 pub fn display_current_time_in_loop(
@@ -142,3 +170,29 @@ pub fn display_timer(adapter: &mut TM1637Adapter, sleep_fn: &dyn Fn(), from_val:
     }
     adapter.clear();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font::AsciiFont;
+
+    #[test]
+    fn test_banner_data() {
+        // the colon must not flicker while the banner scrolls
+        assert_eq!(
+            TM1637Adapter::encode_string("v1 2 "),
+            banner_data(BoardProfile::default(), &AsciiFont::DEFAULT, "v1.2.")
+        );
+        assert_eq!(
+            TM1637Adapter::encode_string("v1 2 "),
+            banner_data(BoardProfile::FOUR_DIGIT, &AsciiFont::DEFAULT, "v1.2.")
+        );
+
+        let point = SegmentBits::SegPoint as u8;
+        let profile = BoardProfile::FOUR_DIGIT.with_point_style(PointStyle::DecimalPoint);
+        let mut expected = TM1637Adapter::encode_string("v12");
+        expected[1] |= point;
+        expected[2] |= point;
+        assert_eq!(expected, banner_data(profile, &AsciiFont::DEFAULT, "v1.2."));
+    }
+}
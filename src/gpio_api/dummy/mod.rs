@@ -22,6 +22,7 @@
 
 //! This module is only for testing with a dummy. This way I can execute it on my
 //! Mac without an actual GPIO interface. Because of this I can set breakpoints and so on..
+//! If you want to check what your code puts on the display, use the `sim` feature instead.
 
 use crate::GpioPinValue::LOW;
 use crate::TM1637Adapter;
@@ -28,13 +28,16 @@
 //!
 //! This feature must be activated in your Cargo.toml if you want to use it.
 
-use crate::{GpioPinValue, TM1637Adapter};
+use crate::{GpioPinValue, TM1637Adapter, TM1637Error};
 use alloc::boxed::Box;
 use alloc::rc::Rc;
 use core::cell::RefCell;
 use gpio::sysfs::{SysFsGpioInput, SysFsGpioOutput};
 use gpio::{GpioIn, GpioOut, GpioValue};
 
+/// Error type of the "gpio"-crate (an I/O error).
+type Error = <SysFsGpioOutput as GpioOut>::Error;
+
 // Abstract:
 // We must prevent that pins get dropped after writing high/low, because this results in an
 // "unexport" in sysfs, which resets the pin.
@@ -46,66 +49,91 @@ enum PinKind {
 }
 
 impl PinKind {
-    fn new_in(pin_num: u16) -> Self {
-        Self::In(SysFsGpioInput::open(pin_num).expect("gpio sysfs: could not open pin"))
-    }
-
-    fn new_out(pin_num: u16) -> Self {
-        Self::Out(SysFsGpioOutput::open(pin_num).expect("gpio sysfs: could not open pin"))
-    }
-
-    fn in_pin(&mut self) -> &mut SysFsGpioInput {
-        if let Self::In(pin) = self {
-            pin
-        } else {
-            panic!("Not an input pin!")
-        }
-    }
-
-    fn out_pin(&mut self) -> &mut SysFsGpioOutput {
-        if let Self::Out(pin) = self {
-            pin
-        } else {
-            panic!("Not an output pin!")
-        }
+    fn new_out(pin_num: u16) -> Result<Self, Error> {
+        SysFsGpioOutput::open(pin_num).map(Self::Out)
     }
 
-    fn in_to_out(pin: &Rc<RefCell<Option<Self>>>, pin_num: u16) {
-        // Reset old Pin due to drop
-        pin.replace(None);
-        pin.replace(Some(Self::new_out(pin_num)));
+    /// Calls `f` with the pin opened as output.
+    ///
+    /// An input pin is dropped (unexported) first, then it can be an output
+    /// pin. If opening fails, the pin stays closed and the next access opens
+    /// it again; the error is returned.
+    fn with_out<T>(
+        pin: &RefCell<Option<Self>>,
+        pin_num: u16,
+        f: impl FnOnce(&mut SysFsGpioOutput) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let mut slot = pin.borrow_mut();
+        let mut out = match slot.take() {
+            Some(Self::Out(out)) => out,
+            other => {
+                drop(other);
+                SysFsGpioOutput::open(pin_num)?
+            }
+        };
+        let result = f(&mut out);
+        *slot = Some(Self::Out(out));
+        result
     }
 
-    fn out_to_in(pin: &Rc<RefCell<Option<Self>>>, pin_num: u16) {
-        // Reset old Pin due to drop
-        pin.replace(None);
-        pin.replace(Some(Self::new_in(pin_num)));
+    /// Like [`PinKind::with_out`] but with the pin opened as input.
+    fn with_in<T>(
+        pin: &RefCell<Option<Self>>,
+        pin_num: u16,
+        f: impl FnOnce(&mut SysFsGpioInput) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let mut slot = pin.borrow_mut();
+        let mut input = match slot.take() {
+            Some(Self::In(input)) => input,
+            other => {
+                drop(other);
+                SysFsGpioInput::open(pin_num)?
+            }
+        };
+        let result = f(&mut input);
+        *slot = Some(Self::In(input));
+        result
     }
 }
 
 /// Sets up the TM1637 Adapter using "gpio"-crate (that uses sysfs) as GPIO interface.
+///
+/// Panics on GPIO errors. Use [`try_setup_gpio`] if you want to handle them.
 pub fn setup_gpio(clk_pin: u16, dio_pin: u16, bit_delay_fn: Box<dyn Fn()>) -> TM1637Adapter {
+    try_setup_gpio(clk_pin, dio_pin, bit_delay_fn)
+        .expect("gpio sysfs: could not open pin")
+        .into_unwrapping()
+}
+
+/// Like [`setup_gpio`] but returns an adapter that propagates GPIO errors
+/// instead of panicking.
+pub fn try_setup_gpio(
+    clk_pin: u16,
+    dio_pin: u16,
+    bit_delay_fn: Box<dyn Fn()>,
+) -> Result<TM1637Adapter<Error>, TM1637Error<Error>> {
     // we must create the pins here
     // there must be references of them while the driver is running
     // otherwise the pins are dropped at every invocation which unexports them
     // which lets the kernel overwrite the last signal we wrote
     // e.g. "1" + unexport => 0 instead of it stays a 1
 
-    let clk_pin = PinKind::new_out(clk_pin);
+    let clk_pin_num = clk_pin;
+    let clk_pin = PinKind::new_out(clk_pin).map_err(TM1637Error::Pin)?;
     let clk_pin = Rc::from(RefCell::from(Option::from(clk_pin)));
 
     let dio_pin_num = dio_pin;
-    let dio_pin = PinKind::new_out(dio_pin);
+    let dio_pin = PinKind::new_out(dio_pin).map_err(TM1637Error::Pin)?;
     let dio_pin = Rc::from(RefCell::from(Option::from(dio_pin)));
 
     // set up all the wrapper functions that connects the tm1637-driver with wiringpi
-    let pin_clock_write_fn = pin_write_fn_factory(clk_pin);
-    let pin_dio_write_fn = pin_write_fn_factory(dio_pin.clone());
-    let pin_dio_read_fn: Box<dyn Fn() -> GpioPinValue> = pin_read_fn_factory(dio_pin, dio_pin_num);
+    let pin_clock_write_fn = pin_write_fn_factory(clk_pin, clk_pin_num);
+    let pin_dio_write_fn = pin_write_fn_factory(dio_pin.clone(), dio_pin_num);
+    let pin_dio_read_fn = pin_read_fn_factory(dio_pin, dio_pin_num);
     // set up delay-fn: thread::sleep() is not available in lib because out lib is no-std
 
     // pass all wrapper functions to the adapter.
-    TM1637Adapter::new(
+    TM1637Adapter::try_new(
         pin_clock_write_fn,
         pin_dio_write_fn,
         pin_dio_read_fn,
@@ -114,43 +142,37 @@ pub fn setup_gpio(clk_pin: u16, dio_pin: u16, bit_delay_fn: Box<dyn Fn()>) -> TM
 }
 
 /// Creates a function/closure for the given pin that changes the value of the pin.
-fn pin_write_fn_factory(pin: Rc<RefCell<Option<PinKind>>>) -> Box<dyn Fn(GpioPinValue)> {
+fn pin_write_fn_factory(
+    pin: Rc<RefCell<Option<PinKind>>>,
+    pin_num: u16,
+) -> Box<dyn Fn(GpioPinValue) -> Result<(), Error>> {
     Box::from(move |bit| {
-        let mut pin = pin.borrow_mut();
-        let pin = pin.as_mut().unwrap();
-        let pin = pin.out_pin();
-        pin.set_value(bit as u8).unwrap();
+        // An input pin is released, i.e. the pull-up resistor keeps the line
+        // high. Opening it as output drives it low first, which is a start
+        // condition if CLK is high. Therefore, it stays an input until it must
+        // be low.
+        if matches!(bit, GpioPinValue::HIGH) && matches!(*pin.borrow(), Some(PinKind::In(_))) {
+            return Ok(());
+        }
+        PinKind::with_out(&pin, pin_num, |out| out.set_value(bit as u8))
     })
 }
 
 /// Creates a function/closure for the given pin that reads its value in the moment of invocation.
-/// It fulfills the contract that the pin will be an out pin after this function is done!
-/// Out-Pins are the default for this interface.
+/// The pin stays an input pin afterwards, i.e. DIO is released until it is
+/// written low.
 fn pin_read_fn_factory(
     pin: Rc<RefCell<Option<PinKind>>>,
     pin_num: u16,
-) -> Box<dyn Fn() -> GpioPinValue> {
+) -> Box<dyn Fn() -> Result<GpioPinValue, Error>> {
     Box::from(move || {
-        // we drop/unexport the pin in out-mode
-        // then it can be an input pin
-        PinKind::out_to_in(&pin, pin_num);
-
-        // read value
-        let res = {
-            // this is a block so that "res" is dropped before we call in_to_out again!
-
-            let mut res = pin.borrow_mut();
-            res.as_mut().unwrap().in_pin().read_value().unwrap()
-        };
-
-        // we drop/unexport the pin in in-mode
-        // then it can be an output pin again
-        PinKind::in_to_out(&pin, pin_num);
+        // the pin is dropped/unexported in out-mode, then it can be an input pin
+        let res = PinKind::with_in(&pin, pin_num, |input| input.read_value())?;
 
         if res == GpioValue::High {
-            GpioPinValue::HIGH
+            Ok(GpioPinValue::HIGH)
         } else {
-            GpioPinValue::LOW
+            Ok(GpioPinValue::LOW)
         }
     })
 }
@@ -26,11 +26,13 @@
 //!
 //! This feature must be activated in your Cargo.toml of you want to use it.
 
-use crate::{GpioPinValue, TM1637Adapter};
+use crate::{GpioPinValue, TM1637Adapter, TM1637Error};
 use alloc::boxed::Box;
+use alloc::format;
 use alloc::rc::Rc;
+use alloc::vec::Vec;
 use core::cell::RefCell;
-use gpio_cdev::{Chip, Line, LineHandle, LineRequestFlags};
+use gpio_cdev::{Chip, Error, Line, LineHandle, LineRequestFlags};
 
 /// Describes the persistent info/state of a "line" which is a Pin in the
 /// character device driver-based terminology. We need this to retain control of
@@ -41,47 +43,52 @@ struct LineState {
 }
 
 impl LineState {
-    fn new(chip: &mut Chip, pin_num: u32) -> Self {
-        let line = chip.get_line(pin_num).unwrap();
-        if line.info().unwrap().is_kernel() {
-            panic!("Pin {} is already used in kernel!", pin_num)
+    fn new(chip: &mut Chip, pin_num: u32) -> Result<Self, TM1637Error<Error>> {
+        let line = chip.get_line(pin_num).map_err(TM1637Error::Pin)?;
+        if line.info().map_err(TM1637Error::Pin)?.is_kernel() {
+            // Pin is already used in kernel!
+            return Err(TM1637Error::Busy);
         }
-        Self {
+        Ok(Self {
             line,
             handle: RefCell::from(None),
-        }
+        })
     }
 
-    fn switch_to_out(ls: &Rc<Self>) {
-        ls.handle.replace(None);
-        ls.handle.replace(Some(
-            ls.line
-                .request(
-                    LineRequestFlags::OUTPUT,
-                    0,
-                    &format!(
-                        "tm1637-adapter-out-pin {}",
-                        ls.line.info().unwrap().line().offset()
-                    ),
-                )
-                .unwrap(),
-        ));
+    /// Calls `f` with a handle of the line as output or input. If the line is
+    /// requested as output here, it starts with the value `default`.
+    ///
+    /// A handle in the other mode is released first because a line can only be
+    /// requested once. If the request fails, the line stays released and the
+    /// next access requests it again; the error is returned.
+    fn with_handle<T>(
+        &self,
+        output: bool,
+        default: u8,
+        f: impl FnOnce(&LineHandle) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let mut slot = self.handle.borrow_mut();
+        let handle = match slot.take() {
+            Some(handle) if handle.flags().contains(LineRequestFlags::OUTPUT) == output => handle,
+            other => {
+                // release the line before we request it again
+                drop(other);
+                let (flags, mode) = if output {
+                    (LineRequestFlags::OUTPUT, "out")
+                } else {
+                    (LineRequestFlags::INPUT, "in")
+                };
+                let label = format!("tm1637-adapter-{mode}-pin {}", self.line.offset());
+                self.line.request(flags, default, &label)?
+            }
+        };
+        let result = f(&handle);
+        *slot = Some(handle);
+        result
     }
 
-    fn switch_to_in(ls: &Rc<Self>) {
-        ls.handle.replace(None);
-        ls.handle.replace(Some(
-            ls.line
-                .request(
-                    LineRequestFlags::INPUT,
-                    0,
-                    &format!(
-                        "tm1637-adapter-in-pin {}",
-                        ls.line.info().unwrap().line().offset()
-                    ),
-                )
-                .unwrap(),
-        ));
+    fn switch_to_out(&self) -> Result<(), Error> {
+        self.with_handle(true, 0, |_| Ok(()))
     }
 }
 
@@ -92,29 +99,45 @@ impl LineState {
 /// See <https://docs.rs/gpio-cdev/0.3.0/gpio_cdev/>
 ///
 /// * `gpio_dev` is probably always "/dev/gpiochip0"
+///
+/// Panics on GPIO errors. Use [`try_setup_gpio_cdev`] if you want to handle them.
 pub fn setup_gpio_cdev(
     clk_pin: u32,
     dio_pin: u32,
     bit_delay_fn: Box<dyn Fn()>,
     gpio_dev: &str,
 ) -> TM1637Adapter {
-    let mut gpio = Chip::new(gpio_dev).unwrap();
+    try_setup_gpio_cdev(clk_pin, dio_pin, bit_delay_fn, gpio_dev)
+        .expect("gpio_cdev: could not set up pins")
+        .into_unwrapping()
+}
+
+/// Like [`setup_gpio_cdev`] but returns an adapter that propagates GPIO
+/// errors instead of panicking. Fails with [`TM1637Error::Busy`] if one of
+/// the pins is already used by the kernel.
+pub fn try_setup_gpio_cdev(
+    clk_pin: u32,
+    dio_pin: u32,
+    bit_delay_fn: Box<dyn Fn()>,
+    gpio_dev: &str,
+) -> Result<TM1637Adapter<Error>, TM1637Error<Error>> {
+    let mut gpio = Chip::new(gpio_dev).map_err(TM1637Error::Pin)?;
 
     // prepare both pins and set them as output!
 
-    let clk_pin = LineState::new(&mut gpio, clk_pin);
+    let clk_pin = LineState::new(&mut gpio, clk_pin)?;
     let clk_pin = Rc::from(clk_pin);
-    LineState::switch_to_out(&clk_pin);
+    clk_pin.switch_to_out().map_err(TM1637Error::Pin)?;
 
-    let dio_pin = LineState::new(&mut gpio, dio_pin);
+    let dio_pin = LineState::new(&mut gpio, dio_pin)?;
     let dio_pin = Rc::from(dio_pin);
-    LineState::switch_to_out(&dio_pin);
+    dio_pin.switch_to_out().map_err(TM1637Error::Pin)?;
 
     let pin_clock_write_fn = pin_write_fn_factory(clk_pin);
     let pin_dio_write_fn = pin_write_fn_factory(dio_pin.clone());
     let pin_dio_read_fn = pin_read_fn_factory(dio_pin);
 
-    TM1637Adapter::new(
+    TM1637Adapter::try_new(
         pin_clock_write_fn,
         pin_dio_write_fn,
         pin_dio_read_fn,
@@ -122,25 +145,56 @@ pub fn setup_gpio_cdev(
     )
 }
 
+/// Scans the candidate lines for connected TM1637s.
+///
+/// Each pair of the lines is probed as CLK and DIO (in both orders) with
+/// [`crate::TM1637::try_probe`]. Returns the `(clk_pin, dio_pin)`-pairs
+/// where a TM1637 answered. Pairs whose lines can't be requested, e.g. because
+/// they are used by the kernel or another process, are skipped.
+///
+/// The probe commits the default display state (on, highest brightness) to
+/// each TM1637 that is found. Only pass lines that are safe to toggle!
+///
+/// Fails only if `gpio_dev` can't be opened.
+pub fn scan_gpio_cdev(
+    candidate_pins: &[u32],
+    bit_delay_fn: impl Fn() + Clone + 'static,
+    gpio_dev: &str,
+) -> Result<Vec<(u32, u32)>, TM1637Error<Error>> {
+    // fail early if the chip doesn't exist
+    Chip::new(gpio_dev).map_err(TM1637Error::Pin)?;
+
+    let mut found = Vec::new();
+    for &clk_pin in candidate_pins {
+        for &dio_pin in candidate_pins.iter().filter(|&&pin| pin != clk_pin) {
+            let probe =
+                try_setup_gpio_cdev(clk_pin, dio_pin, Box::from(bit_delay_fn.clone()), gpio_dev)
+                    .and_then(|adapter| adapter.try_probe());
+            if matches!(probe, Ok(true)) {
+                found.push((clk_pin, dio_pin));
+            }
+            // the lines are released when the adapter is dropped
+        }
+    }
+    Ok(found)
+}
+
 /// Creates a function/closure for the given pin that changes the value of the pin.
-fn pin_write_fn_factory(pin: Rc<LineState>) -> Box<dyn Fn(GpioPinValue)> {
-    Box::from(move |bit| {
-        let h = pin.handle.borrow_mut();
-        let h = h.as_ref().unwrap();
-        h.set_value(bit as u8).unwrap();
-    })
+fn pin_write_fn_factory(pin: Rc<LineState>) -> Box<dyn Fn(GpioPinValue) -> Result<(), Error>> {
+    // an input line is requested with the new value right away, otherwise it
+    // would be low for a moment, which is a start condition if CLK is high
+    Box::from(move |bit| pin.with_handle(true, bit as u8, |h| h.set_value(bit as u8)))
 }
 
 /// Creates a function/closure for the given pin that reads its value in the moment of invocation.
-fn pin_read_fn_factory(pin: Rc<LineState>) -> Box<dyn Fn() -> GpioPinValue> {
+/// The line stays an input afterwards, i.e. DIO is released until the next write.
+fn pin_read_fn_factory(pin: Rc<LineState>) -> Box<dyn Fn() -> Result<GpioPinValue, Error>> {
     Box::from(move || {
-        LineState::switch_to_in(&pin);
-        let res = pin.handle.borrow().as_ref().unwrap().get_value().unwrap();
-        LineState::switch_to_out(&pin);
+        let res = pin.with_handle(false, 0, LineHandle::get_value)?;
         if res == 0 {
-            GpioPinValue::LOW
+            Ok(GpioPinValue::LOW)
         } else {
-            GpioPinValue::HIGH
+            Ok(GpioPinValue::HIGH)
         }
     })
 }
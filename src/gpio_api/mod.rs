@@ -39,7 +39,7 @@ pub use super::gpio_api::wiringpi::setup_wiringpi;
 #[cfg(feature = "gpio-api-gpio")]
 mod gpio;
 #[cfg(feature = "gpio-api-gpio")]
-pub use super::gpio_api::gpio::setup_gpio;
+pub use super::gpio_api::gpio::{setup_gpio, try_setup_gpio};
 
 // #############################################################################
 
@@ -47,7 +47,7 @@ pub use super::gpio_api::gpio::setup_gpio;
 #[cfg(feature = "gpio-api-sysfs_gpio")]
 mod sysfs_gpio;
 #[cfg(feature = "gpio-api-sysfs_gpio")]
-pub use super::gpio_api::sysfs_gpio::setup_sysfs_gpio;
+pub use super::gpio_api::sysfs_gpio::{setup_sysfs_gpio, try_setup_sysfs_gpio};
 
 // #############################################################################
 
@@ -55,7 +55,7 @@ pub use super::gpio_api::sysfs_gpio::setup_sysfs_gpio;
 #[cfg(feature = "gpio-api-gpio_cdev")]
 mod gpio_cdev;
 #[cfg(feature = "gpio-api-gpio_cdev")]
-pub use super::gpio_api::gpio_cdev::setup_gpio_cdev;
+pub use super::gpio_api::gpio_cdev::{scan_gpio_cdev, setup_gpio_cdev, try_setup_gpio_cdev};
 
 // #############################################################################
 
@@ -27,20 +27,34 @@
 //!
 //! This feature must be activated in your Cargo.toml of you want to use it.
 
-use crate::{GpioPinValue, TM1637Adapter};
+use crate::{GpioPinValue, TM1637Adapter, TM1637Error};
 use alloc::boxed::Box;
-use sysfs_gpio::{Direction, Pin};
+use sysfs_gpio::{Direction, Error, Pin};
 
 /// Sets up the TM1637 Adapter using WiringPi as GPIO interface.
+///
+/// Panics on GPIO errors. Use [`try_setup_sysfs_gpio`] if you want to handle them.
 pub fn setup_sysfs_gpio(clk_pin: u64, dio_pin: u64, bit_delay_fn: Box<dyn Fn()>) -> TM1637Adapter {
+    try_setup_sysfs_gpio(clk_pin, dio_pin, bit_delay_fn)
+        .expect("sysfs_gpio: could not set up pins")
+        .into_unwrapping()
+}
+
+/// Like [`setup_sysfs_gpio`] but returns an adapter that propagates GPIO
+/// errors instead of panicking.
+pub fn try_setup_sysfs_gpio(
+    clk_pin: u64,
+    dio_pin: u64,
+    bit_delay_fn: Box<dyn Fn()>,
+) -> Result<TM1637Adapter<Error>, TM1637Error<Error>> {
     // set up all the wrapper functions that connects the tm1637-driver with wiringpi
     let pin_clock_write_fn = pin_write_fn_factory(clk_pin);
     let pin_dio_write_fn = pin_write_fn_factory(dio_pin);
-    let pin_dio_read_fn: Box<dyn Fn() -> GpioPinValue> = pin_read_fn_factory(dio_pin);
+    let pin_dio_read_fn = pin_read_fn_factory(dio_pin);
     // set up delay-fn: sleep() is not available in our lib because we use no-std
 
     // pass all wrapper functions to the adapter.
-    TM1637Adapter::new(
+    TM1637Adapter::try_new(
         pin_clock_write_fn,
         pin_dio_write_fn,
         pin_dio_read_fn,
@@ -49,26 +63,31 @@ pub fn setup_sysfs_gpio(clk_pin: u64, dio_pin: u64, bit_delay_fn: Box<dyn Fn()>)
 }
 
 /// Creates a function/closure for the given pin that changes the value of the pin.
-fn pin_write_fn_factory(pin_num: u64) -> Box<dyn Fn(GpioPinValue)> {
+fn pin_write_fn_factory(pin_num: u64) -> Box<dyn Fn(GpioPinValue) -> Result<(), Error>> {
     Box::from(move |bit| {
         let pin = Pin::new(pin_num);
-        pin.export().unwrap();
-        pin.set_direction(Direction::Out).unwrap();
-        pin.set_value(bit as u8).unwrap();
+        pin.export()?;
+        // "high"/"low" switch an input pin to output with the value right away;
+        // "out" would drive it low first, which is a start condition if CLK is high
+        match bit {
+            GpioPinValue::HIGH => pin.set_direction(Direction::High),
+            GpioPinValue::LOW => pin.set_direction(Direction::Low),
+        }
     })
 }
 
 /// Creates a function/closure for the given pin that reads its value in the moment of invocation.
-fn pin_read_fn_factory(pin_num: u64) -> Box<dyn Fn() -> GpioPinValue> {
+/// The pin stays an input pin afterwards, i.e. DIO is released until the next write.
+fn pin_read_fn_factory(pin_num: u64) -> Box<dyn Fn() -> Result<GpioPinValue, Error>> {
     Box::from(move || {
         let pin = Pin::new(pin_num);
-        pin.export().unwrap();
-        pin.set_direction(Direction::In).unwrap();
-        let res = pin.get_value().unwrap();
+        pin.export()?;
+        pin.set_direction(Direction::In)?;
+        let res = pin.get_value()?;
         if res == 0 {
-            GpioPinValue::LOW
+            Ok(GpioPinValue::LOW)
         } else {
-            GpioPinValue::HIGH
+            Ok(GpioPinValue::HIGH)
         }
     })
 }
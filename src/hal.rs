@@ -0,0 +1,208 @@
+// MIT License
+//
+// Copyright (c) 2022 Philipp Schuster <phip1611@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Provides [`EmbeddedHalPins`] to use the driver with any implementation of
+//! the [`embedded_hal`] 1.0 traits, for example microcontroller HALs or
+//! `linux-embedded-hal`.
+//!
+//! This feature must be activated in your Cargo.toml if you want to use it.
+
+use crate::{GpioPinValue, TM1637, TM1637Error, Tm1637Pins};
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{Error, ErrorKind, InputPin, OutputPin, PinState};
+
+/// [`Tm1637Pins`] backed by [`embedded_hal`] pins and a delay.
+///
+/// DIO must be an open-drain pin (with a pull-up resistor) that can be read
+/// while it is released, which is what most HALs provide as an open-drain
+/// output that also implements [`InputPin`]. Setting it high releases it, see
+/// [`Tm1637Pins::release_dio`]. A push-pull pin would drive DIO high while
+/// the TM1637 pulls it low.
+///
+/// Errors of the pins are reported by their [`ErrorKind`] because CLK and DIO
+/// may have different error types.
+#[derive(Debug)]
+pub struct EmbeddedHalPins<CLK, DIO, DELAY> {
+    clk: CLK,
+    dio: DIO,
+    delay: DELAY,
+    bit_delay_ns: u32,
+}
+
+impl<CLK, DIO, DELAY> EmbeddedHalPins<CLK, DIO, DELAY>
+where
+    CLK: OutputPin,
+    DIO: OutputPin + InputPin,
+    DELAY: DelayNs,
+{
+    /// Creates the pins for the driver.
+    ///
+    /// * `clk` output pin connected to CLK
+    /// * `dio` open-drain pin connected to DIO
+    /// * `delay` delay provider
+    /// * `bit_delay_ns` delay after a bit has been written to a pin. The TM1637
+    ///   supports clock frequencies up to 250kHz, i.e. 2000ns are fine.
+    pub const fn new(clk: CLK, dio: DIO, delay: DELAY, bit_delay_ns: u32) -> Self {
+        Self {
+            clk,
+            dio,
+            delay,
+            bit_delay_ns,
+        }
+    }
+
+    /// Returns the pins and the delay provider.
+    pub fn release(self) -> (CLK, DIO, DELAY) {
+        (self.clk, self.dio, self.delay)
+    }
+}
+
+impl<CLK, DIO, DELAY> Tm1637Pins for EmbeddedHalPins<CLK, DIO, DELAY>
+where
+    CLK: OutputPin,
+    DIO: OutputPin + InputPin,
+    DELAY: DelayNs,
+{
+    type Error = ErrorKind;
+
+    fn write_clk(&mut self, value: GpioPinValue) -> Result<(), ErrorKind> {
+        self.clk.set_state(value.into()).map_err(|e| e.kind())
+    }
+
+    fn write_dio(&mut self, value: GpioPinValue) -> Result<(), ErrorKind> {
+        self.dio.set_state(value.into()).map_err(|e| e.kind())
+    }
+
+    fn release_dio(&mut self) -> Result<(), ErrorKind> {
+        // DIO is open-drain: high means released
+        self.dio.set_high().map_err(|e| e.kind())
+    }
+
+    fn read_dio(&mut self) -> Result<GpioPinValue, ErrorKind> {
+        match self.dio.is_high() {
+            Ok(true) => Ok(GpioPinValue::HIGH),
+            Ok(false) => Ok(GpioPinValue::LOW),
+            Err(e) => Err(e.kind()),
+        }
+    }
+
+    fn bit_delay(&mut self) {
+        self.delay.delay_ns(self.bit_delay_ns)
+    }
+
+    fn delay_ns(&mut self, ns: u32) {
+        self.delay.delay_ns(ns)
+    }
+}
+
+impl From<GpioPinValue> for PinState {
+    fn from(value: GpioPinValue) -> Self {
+        match value {
+            GpioPinValue::LOW => Self::Low,
+            GpioPinValue::HIGH => Self::High,
+        }
+    }
+}
+
+impl<CLK, DIO, DELAY> TM1637<EmbeddedHalPins<CLK, DIO, DELAY>>
+where
+    CLK: OutputPin,
+    DIO: OutputPin + InputPin,
+    DELAY: DelayNs,
+{
+    /// Creates a new driver from [`embedded_hal`] pins. See
+    /// [`EmbeddedHalPins::new`] for the parameters.
+    pub fn from_embedded_hal(
+        clk: CLK,
+        dio: DIO,
+        delay: DELAY,
+        bit_delay_ns: u32,
+    ) -> Result<Self, TM1637Error<ErrorKind>> {
+        Self::from_pins(EmbeddedHalPins::new(clk, dio, delay, bit_delay_ns))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::convert::Infallible;
+    use embedded_hal::digital::ErrorType;
+
+    /// DIO that is always pulled low by the (simulated) TM1637.
+    struct AckingDio;
+
+    impl ErrorType for AckingDio {
+        type Error = Infallible;
+    }
+
+    impl OutputPin for AckingDio {
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            Ok(())
+        }
+    }
+
+    impl InputPin for AckingDio {
+        fn is_high(&mut self) -> Result<bool, Infallible> {
+            Ok(false)
+        }
+
+        fn is_low(&mut self) -> Result<bool, Infallible> {
+            Ok(true)
+        }
+    }
+
+    /// CLK that fails on every access.
+    struct BrokenClk;
+
+    impl ErrorType for BrokenClk {
+        type Error = ErrorKind;
+    }
+
+    impl OutputPin for BrokenClk {
+        fn set_low(&mut self) -> Result<(), ErrorKind> {
+            Err(ErrorKind::Other)
+        }
+
+        fn set_high(&mut self) -> Result<(), ErrorKind> {
+            Err(ErrorKind::Other)
+        }
+    }
+
+    struct NoDelay;
+
+    impl DelayNs for NoDelay {
+        fn delay_ns(&mut self, _ns: u32) {}
+    }
+
+    #[test]
+    fn test_embedded_hal_pins() {
+        let display = TM1637::from_embedded_hal(AckingDio, AckingDio, NoDelay, 0).unwrap();
+        assert_eq!(Ok(()), display.try_write_segments_raw(&[0xff; 4], 0));
+
+        let display = TM1637::from_embedded_hal(BrokenClk, AckingDio, NoDelay, 0);
+        assert_eq!(Some(TM1637Error::Pin(ErrorKind::Other)), display.err());
+    }
+}
@@ -0,0 +1,164 @@
+// MIT License
+//
+// Copyright (c) 2022 Philipp Schuster <phip1611@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Types for the key-scan matrix of the TM1637.
+//!
+//! The TM1637 can scan up to 16 keys that are wired between one of the two
+//! key lines (K1, K2) and one of the eight segment lines (SG1 to SG8). The
+//! scan result is read via the "read key scan data"-command. Only one key at
+//! a time can be detected.
+
+/// Key line of the key-scan matrix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum KeyLine {
+    /// Key line K1.
+    K1,
+    /// Key line K2.
+    K2,
+}
+
+/// Segment line of the key-scan matrix.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum KeySegment {
+    /// Segment line SG1.
+    SG1 = 0,
+    /// Segment line SG2.
+    SG2 = 1,
+    /// Segment line SG3.
+    SG3 = 2,
+    /// Segment line SG4.
+    SG4 = 3,
+    /// Segment line SG5.
+    SG5 = 4,
+    /// Segment line SG6.
+    SG6 = 5,
+    /// Segment line SG7.
+    SG7 = 6,
+    /// Segment line SG8.
+    SG8 = 7,
+}
+
+impl KeySegment {
+    /// Returns the segment line for the lower 3 bits of `bits`.
+    const fn from_bits(bits: u8) -> Self {
+        match bits & 0b111 {
+            0 => Self::SG1,
+            1 => Self::SG2,
+            2 => Self::SG3,
+            3 => Self::SG4,
+            4 => Self::SG5,
+            5 => Self::SG6,
+            6 => Self::SG7,
+            _ => Self::SG8,
+        }
+    }
+}
+
+/// Decoded result of a key scan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyCode {
+    /// No key is pressed.
+    NoKey,
+    /// The key between K1 and the given segment line is pressed.
+    K1(KeySegment),
+    /// The key between K2 and the given segment line is pressed.
+    K2(KeySegment),
+}
+
+impl KeyCode {
+    /// Scan code that the TM1637 reports if no key is pressed.
+    pub const NO_KEY_SCAN_CODE: u8 = 0xff;
+
+    /// Decodes the raw scan code that was read from the TM1637 (bits received
+    /// LSB first). Returns `None` if the value is not a valid scan code, which
+    /// usually indicates a transmission error.
+    ///
+    /// According to the data sheet the codes are `0xf7` (K1/SG1) down to `0xf0`
+    /// (K1/SG8) and `0xef` (K2/SG1) down to `0xe8` (K2/SG8).
+    pub const fn from_scan_code(code: u8) -> Option<Self> {
+        // the TM1637 pulls the lines low; inverted, bit 3 stands for K1,
+        // bit 4 for K2 and bits 2-0 for the segment line
+        let inverted = !code;
+        match inverted & 0b1111_1000 {
+            0b0000_0000 if code == Self::NO_KEY_SCAN_CODE => Some(Self::NoKey),
+            0b0000_1000 => Some(Self::K1(KeySegment::from_bits(inverted))),
+            0b0001_0000 => Some(Self::K2(KeySegment::from_bits(inverted))),
+            _ => None,
+        }
+    }
+
+    /// Returns the raw scan code as it would be reported by the TM1637.
+    pub const fn scan_code(self) -> u8 {
+        match self {
+            Self::NoKey => Self::NO_KEY_SCAN_CODE,
+            Self::K1(sg) => !(0b0000_1000 | sg as u8),
+            Self::K2(sg) => !(0b0001_0000 | sg as u8),
+        }
+    }
+
+    /// Returns the key line and the segment line of the pressed key, if any.
+    pub const fn key(self) -> Option<(KeyLine, KeySegment)> {
+        match self {
+            Self::NoKey => None,
+            Self::K1(sg) => Some((KeyLine::K1, sg)),
+            Self::K2(sg) => Some((KeyLine::K2, sg)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_scan_codes() {
+        assert_eq!(Some(KeyCode::NoKey), KeyCode::from_scan_code(0xff));
+        assert_eq!(
+            Some(KeyCode::K1(KeySegment::SG1)),
+            KeyCode::from_scan_code(0xf7)
+        );
+        assert_eq!(
+            Some(KeyCode::K1(KeySegment::SG8)),
+            KeyCode::from_scan_code(0xf0)
+        );
+        assert_eq!(
+            Some(KeyCode::K2(KeySegment::SG1)),
+            KeyCode::from_scan_code(0xef)
+        );
+        assert_eq!(
+            Some(KeyCode::K2(KeySegment::SG8)),
+            KeyCode::from_scan_code(0xe8)
+        );
+        assert_eq!(None, KeyCode::from_scan_code(0x00));
+        assert_eq!(None, KeyCode::from_scan_code(0xe7));
+    }
+
+    #[test]
+    fn test_scan_code_roundtrip() {
+        for code in 0..=u8::MAX {
+            if let Some(key) = KeyCode::from_scan_code(code) {
+                assert_eq!(code, key.scan_code());
+            }
+        }
+    }
+}
@@ -49,21 +49,65 @@
 //! Feel free to contribute. :)
 
 // from rust core library; no "external crate" in the manner that this is no crates.io dependency;
-// needed because no_std
-#[macro_use] // wee need the !format macro
+// needed because no_std; optional so that the driver can be used on systems without a heap
+#[cfg(any(feature = "alloc", test))]
 extern crate alloc;
+// only for the calibrated busy-wait delay of `timing::BusyWait`
+#[cfg(feature = "std")]
+extern crate std;
 
 // Import our enums/arrays for the symbol mappings to the 7 segment display
+pub mod analyzer;
+#[cfg(feature = "async")]
+pub mod asynch;
+pub mod board;
+pub mod encoding;
+mod error;
+pub mod font;
 #[cfg(feature = "fourdigit7segdis")]
 pub mod fourdigit7segdis;
+pub mod keys;
 pub mod mappings;
+mod pins;
+mod protocol;
+pub mod render;
 // provides conditionally bindings to specific GPIO interfaces; can be activated via cargo features
+#[cfg(feature = "alloc")]
 pub mod gpio_api;
+#[cfg(feature = "embedded-hal")]
+pub mod hal;
+#[cfg(feature = "sim")]
+pub mod sim;
+pub mod timing;
+#[cfg(feature = "alloc")]
+pub mod trace;
+pub mod writer;
 
+pub use error::{GlyphRegistryFullError, InvalidValueError, NumberOverflowError, TM1637Error};
+#[cfg(feature = "alloc")]
+pub use pins::CallbackPins;
+pub use pins::Tm1637Pins;
+
+use crate::board::{BoardProfile, Orientation, PointStyle};
+use crate::font::{AsciiFont, Font};
+use crate::keys::KeyCode;
+use crate::mappings::SegmentBits;
+use crate::protocol::{Frame, Retransmits};
+#[cfg(feature = "alloc")]
+use crate::timing::PinEventKind;
+use crate::timing::TimingProfile;
+#[cfg(feature = "alloc")]
+use crate::trace::Trace;
+use crate::writer::DisplayWriter;
 // to use Box: we don't have std::prelude here
-use crate::mappings::{LoCharBits, NumCharBits, SpecialCharBits, UpCharBits};
+#[cfg(feature = "alloc")]
 use alloc::boxed::Box;
+#[cfg(feature = "alloc")]
+use alloc::rc::Rc;
+#[cfg(feature = "alloc")]
 use alloc::vec::Vec;
+use core::cell::{Cell, RefCell};
+use core::convert::Infallible;
 use core::fmt::{Debug, Formatter};
 
 //       A
@@ -79,9 +123,16 @@ use core::fmt::{Debug, Formatter};
 /// 7-segment display from AzDelivery only uses 4.
 pub const DISPLAY_REGISTERS_COUNT: usize = 6;
 
+/// Number of clocks that [`TM1637::try_recover_bus`] clocks out: the bits of a
+/// byte and its ACK.
+const RECOVERY_CLOCKS: u8 = 9;
+
+/// Marks all display registers of the frame buffer as changed.
+const ALL_REGISTERS_DIRTY: u8 = (1 << DISPLAY_REGISTERS_COUNT) - 1;
+
 /// The value of a GPIO pin.
 #[repr(u8)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum GpioPinValue {
     /// Low.
     LOW,
@@ -95,43 +146,76 @@ impl From<u8> for GpioPinValue {
     }
 }
 
-/// Adapter between your code and the TM1637 via GPIO interface.
-///
-/// You can use the GPIO interface/library that you want. Just provide
-/// the corresponding "glue" functions so that this adapter can access GPIO.
+/// Driver for the TM1637 that talks to it via the given [`Tm1637Pins`].
 ///
-/// Be wise when you choose a value for `bit_delay_us`. This delay is important
-/// to ensure that changed signals are actually on the pins. My experience showed
-/// that 100 (µs) is a safe value on the Raspberry Pi.
-pub struct TM1637Adapter {
-    /// Function that writes the value on the GPIO pin that acts as the clock.
-    pin_clock_write_fn: Box<dyn Fn(GpioPinValue)>,
-    /// Function that writes the value on the GPIO pin that acts as data in and out.
-    pin_dio_write_fn: Box<dyn Fn(GpioPinValue)>,
-    /// Function that reads from the data in and out pin.
-    pin_dio_read_fn: Box<dyn Fn() -> GpioPinValue>,
-    /// Delay function after data bits and clock bits have been set. This may be necessary
-    /// on some hardware.
-    bit_delay_fn: Box<dyn Fn()>,
+/// Use `TM1637Adapter` (feature `alloc`) if you want to provide the "glue" functions to your
+/// GPIO interface/library as closures. Use [`TM1637::from_pins`] with your own
+/// [`Tm1637Pins`] implementation otherwise. `F` is the font of the text that is
+/// written via the driver, see [`TM1637::with_font`].
+pub struct TM1637<P: Tm1637Pins, F: Font = AsciiFont> {
+    /// The pins that are connected to the TM1637.
+    pins: RefCell<P>,
     /// Representation of the display state in bits for the TM1637.
     /// Bits 7-4 are zero. Later the "display control"-command prefix will be there.
     /// Bits 3-0 are for display on/off and brightness.
     brightness: u8,
+    /// How acknowledges of the TM1637 are awaited.
+    ack_policy: AckPolicy,
+    /// When the bus is resynchronized automatically.
+    bus_recovery: BusRecovery,
+    /// How long the phases of the bus signals last. If `None`, the bit delay
+    /// of the pins is used for all of them.
+    timing_profile: Option<TimingProfile>,
+    /// How the digits of the display module are wired to the grids.
+    board_profile: BoardProfile,
+    /// How the display module is mounted.
+    orientation: Orientation,
+    /// Font of the text that is written via the driver.
+    font: F,
+    /// Shadow copy of the display registers. Changed by the `set_segment*`-methods
+    /// and transmitted by [`TM1637::flush`].
+    frame: Cell<[u8; DISPLAY_REGISTERS_COUNT]>,
+    /// Bit `i` is set if display register `i` on the TM1637 differs from
+    /// `frame` (or if its content is unknown).
+    dirty: Cell<u8>,
+    /// The value of `brightness` that was committed last or `None`
+    /// if the state on the TM1637 is unknown.
+    committed_brightness: Cell<Option<u8>>,
 }
 
-impl Debug for TM1637Adapter {
+impl<P: Tm1637Pins + Debug, F: Font> Debug for TM1637<P, F> {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
-        f.debug_struct("TM1637Adapter")
+        f.debug_struct("TM1637")
             // cast to pointer: print as hex
             .field("brightness", &(self.brightness as *const u8))
-            .field("pin_clock_write_fn", &"<func>")
-            .field("pin_dio_write_fn", &"<func>")
-            .field("pin_dio_read_fn", &"<func>")
-            .field("bit_delay_fn", &"<func>")
+            .field("ack_policy", &self.ack_policy)
+            .field("bus_recovery", &self.bus_recovery)
+            .field("timing_profile", &self.timing_profile)
+            .field("board_profile", &self.board_profile)
+            .field("orientation", &self.orientation)
+            .field("frame", &self.frame.get())
+            .field("dirty", &self.dirty.get())
+            .field("pins", &self.pins)
             .finish()
     }
 }
 
+/// Adapter between your code and the TM1637 via GPIO interface.
+///
+/// You can use the GPIO interface/library that you want. Just provide
+/// the corresponding "glue" functions so that this adapter can access GPIO.
+///
+/// Be wise when you choose a value for `bit_delay_us`. This delay is important
+/// to ensure that changed signals are actually on the pins. My experience showed
+/// that 100 (µs) is a safe value on the Raspberry Pi.
+///
+/// `E` is the error type of the GPIO functions. Adapters created with
+/// [`TM1637Adapter::new`] can't fail on GPIO level and use [`Infallible`].
+/// Adapters created with [`TM1637Adapter::try_new`] propagate errors of the
+/// GPIO functions to the caller of the `try_*`-methods.
+#[cfg(feature = "alloc")]
+pub type TM1637Adapter<E = Infallible, F = AsciiFont> = TM1637<CallbackPins<E>, F>;
+
 /// The level of brightness.
 /// The TM1637 "DisplayControl"-command transports the brightness information
 /// in bits 0 to 2.
@@ -156,6 +240,31 @@ pub enum Brightness {
     L7 = 0b111,
 }
 
+impl From<Brightness> for u8 {
+    fn from(brightness: Brightness) -> Self {
+        brightness as Self
+    }
+}
+
+impl TryFrom<u8> for Brightness {
+    type Error = InvalidValueError;
+
+    /// Converts the level 0 to 7 into the brightness.
+    fn try_from(level: u8) -> Result<Self, Self::Error> {
+        match level {
+            0b000 => Ok(Self::L0),
+            0b001 => Ok(Self::L1),
+            0b010 => Ok(Self::L2),
+            0b011 => Ok(Self::L3),
+            0b100 => Ok(Self::L4),
+            0b101 => Ok(Self::L5),
+            0b110 => Ok(Self::L6),
+            0b111 => Ok(Self::L7),
+            _ => Err(InvalidValueError(level)),
+        }
+    }
+}
+
 /// Whether the display is on or off.
 /// The TM1637 "DisplayControl"-command transports the display on/off information
 /// in the third bit (2^3) of the command.
@@ -168,6 +277,71 @@ pub enum DisplayState {
     ON = 0b1000,
 }
 
+impl From<DisplayState> for u8 {
+    fn from(ds: DisplayState) -> Self {
+        ds as Self
+    }
+}
+
+impl TryFrom<u8> for DisplayState {
+    type Error = InvalidValueError;
+
+    /// Converts the bit of the "display control"-command (`0b0000` or
+    /// `0b1000`) into the display state.
+    fn try_from(bits: u8) -> Result<Self, Self::Error> {
+        match bits {
+            0b0000 => Ok(Self::OFF),
+            0b1000 => Ok(Self::ON),
+            _ => Err(InvalidValueError(bits)),
+        }
+    }
+}
+
+/// Describes how the adapter waits for the acknowledge (ACK) that the TM1637
+/// sends after each byte and what happens if it is missing (NACK).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AckPolicy {
+    /// How often DIO is sampled until the TM1637 must have acknowledged a
+    /// byte. There is one bit delay between two samples. At least one sample
+    /// is taken.
+    pub timeout: u16,
+    /// How often a frame (start condition, bytes, stop condition) is
+    /// retransmitted after a NACK. `0` disables retransmission.
+    pub retransmits: u8,
+}
+
+impl Default for AckPolicy {
+    fn default() -> Self {
+        Self {
+            timeout: 10,
+            retransmits: 0,
+        }
+    }
+}
+
+/// Describes when the driver resynchronizes the bus with
+/// [`TM1637::try_recover_bus`] on its own.
+///
+/// This helps if a previous process was killed in the middle of a frame and
+/// left the TM1637 waiting for more bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BusRecovery {
+    /// Recover the bus when the driver is created, see
+    /// [`TM1637::from_pins_with_bus_recovery`].
+    pub on_init: bool,
+    /// Recover the bus after the TM1637 didn't acknowledge a byte, before the
+    /// frame is retransmitted or the error is returned.
+    pub after_nack: bool,
+}
+
+impl BusRecovery {
+    /// Recover the bus on init and after each NACK.
+    pub const ALWAYS: Self = Self {
+        on_init: true,
+        after_nack: true,
+    };
+}
+
 /// The "ISA"/Commands of the TM1637. See data sheet
 /// for more information. This is only a subset of the possible values.
 #[repr(u8)]
@@ -176,6 +350,14 @@ pub enum ISA {
     /// Start instruction. "write data to display register"-mode.
     DataCommandWriteToDisplay = 0b0100_0000,
 
+    /// Start instruction. "read key scan data"-mode. The TM1637 answers with
+    /// one byte that describes the currently pressed key.
+    DataCommandReadKeys = 0b0100_0010,
+
+    /// Start instruction. "write data to display register"-mode with fixed
+    /// address. Each data byte is preceded by its own address command.
+    DataCommandFixedAddress = 0b0100_0100,
+
     /// Base command for the display address. Bits 2-0 specify the display (0-5).
     /// If not deactivated, the device does an internal increment of the display address
     /// as bytes are written.
@@ -187,32 +369,367 @@ pub enum ISA {
     DisplayCommandBase = 0b1000_0000,
 }
 
+#[cfg(feature = "alloc")]
 impl TM1637Adapter {
     /// Creates a new object to interact via GPIO with a TM1637.
     /// Activates the display and set's the brightness to the highest value.
     ///
     /// * `pin_clock_write_fn` function to write bit to CLK pin
     /// * `pin_dio_write_fn` function to write bit to DIO pin
-    /// * `pin_dio_read_fn` function to read value from DIO pin
+    /// * `pin_dio_read_fn` function to read value from DIO pin. It must leave
+    ///   DIO released (input), the driver also calls it to release DIO.
     /// * `bit_delay_fn` function that is invoked after a bit has been written to a pin.
     ///   It depends on your hardware and your GPIO driver. Sometimes 0 is even fine.
+    ///
+    /// If your GPIO functions can fail, use [`TM1637Adapter::try_new`] instead.
     pub fn new(
         pin_clock_write_fn: Box<dyn Fn(GpioPinValue)>,
         pin_dio_write_fn: Box<dyn Fn(GpioPinValue)>,
         pin_dio_read_fn: Box<dyn Fn() -> GpioPinValue>,
         bit_delay_fn: Box<dyn Fn()>,
     ) -> Self {
-        // assume both are already output pins - this is the contract that needs to be fulfilled!
-        (pin_clock_write_fn)(GpioPinValue::LOW);
-        (pin_dio_write_fn)(GpioPinValue::LOW);
+        Self::try_new(
+            Box::from(move |bit| {
+                pin_clock_write_fn(bit);
+                Ok(())
+            }),
+            Box::from(move |bit| {
+                pin_dio_write_fn(bit);
+                Ok(())
+            }),
+            Box::from(move || Ok(pin_dio_read_fn())),
+            bit_delay_fn,
+        )
+        .unwrap_or_else(|_| unreachable!("infallible GPIO functions"))
+    }
 
-        Self {
+    /// Encodes a number from 0 to 9999 on the display.
+    /// See [`encoding::encode_number`].
+    pub fn encode_number(num: u16) -> [u8; 4] {
+        encoding::encode_number(num)
+    }
+
+    /// Encodes a number/digit from 0 to 9 to it's bit representation on the display.
+    /// See [`encoding::encode_digit`].
+    pub const fn encode_digit(digit: u8) -> u8 {
+        encoding::encode_digit(digit)
+    }
+
+    /// Encodes a char for the 7-segment display.
+    /// See [`encoding::encode_char`].
+    pub const fn encode_char(c: char) -> u8 {
+        encoding::encode_char(c)
+    }
+
+    /// Encodes a string for the 7-segment display.
+    /// See [`encoding::encode_string`].
+    pub fn encode_string(str: &str) -> Vec<u8> {
+        encoding::encode_string(str)
+    }
+}
+
+impl<P: Tm1637Pins<Error = Infallible>, F: Font> TM1637<P, F> {
+    /// Writes all raw segments data beginning at the position into the display registers.
+    /// It uses auto increment internally to write into all further registers.
+    /// This functions does an internal check so that not more than 6 registers can be
+    /// addressed/written.
+    /// * `segments` Raw data describing the bits of the 7 segment display.
+    /// * `n` Length of segments array.
+    /// * `pos` The start position of the display register. While bytes are
+    ///   written, address is adjusted internally via auto increment. Usually
+    ///   this is 0, if you want to write data to all 7 segment displays.
+    ///
+    /// Transmission errors are ignored. Use [`TM1637::try_write_segments_raw`]
+    /// if you want to know about them.
+    pub fn write_segments_raw(&self, segments: &[u8], pos: u8) {
+        // being a little bit more failure tolerant
+        if segments.is_empty() {
+            return;
+        } // nothing to do
+        let pos = pos as usize % DISPLAY_REGISTERS_COUNT; // only valid positions/registers
+
+        // valid values are
+        //   n = 1, pos = {0, 1, 2, 3, 4, 5}
+        //   n = 2, pos = {0, 1, 2, 3, 4}
+        //   n = 3, pos = {0, 1, 2, 3}
+        //   n = 4, pos = {0, 1, 2}
+        //   n = 5, pos = {0, 1}
+        //   n = 6, pos = {0}
+        // => n + pos must be <= DISPLAY_REGISTERS_COUNT
+
+        // only write as much data as registers are available; the length is
+        // compared as usize so that long slices don't wrap around
+        let n = segments.len().min(DISPLAY_REGISTERS_COUNT - pos);
+
+        let _ = self.try_write_segments_raw(&segments[..n], pos as u8);
+    }
+
+    /// This uses fixed address mode (see data sheet) internally to write data to
+    /// a specific position of the display.
+    /// Position is 0 to 5.
+    pub fn write_segment_raw(&self, segments: u8, position: u8) {
+        // being a little bit more failure tolerant; only valid positions/registers
+        let position = position % DISPLAY_REGISTERS_COUNT as u8;
+        let _ = self.try_write_segment_raw(segments, position);
+    }
+
+    /// Writes raw segments data to arbitrary positions of the display.
+    /// See [`TM1637::try_write_segments_fixed`].
+    ///
+    /// Errors are ignored. Nothing is written if a position is invalid.
+    pub fn write_segments_fixed(&self, segments: &[(u8, u8)]) {
+        let _ = self.try_write_segments_fixed(segments);
+    }
+
+    /// Send command that sets the display state on the micro controller.
+    pub fn write_display_state(&self) {
+        let _ = self.try_write_display_state();
+    }
+
+    /// Checks whether a TM1637 is connected to the pins.
+    /// See [`TM1637::try_probe`].
+    pub fn probe(&self) -> bool {
+        self.try_probe().unwrap_or(false)
+    }
+
+    /// Clears the display.
+    pub fn clear(&self) {
+        let _ = self.try_clear();
+    }
+
+    /// Transmits the changes of the frame buffer to the TM1637.
+    /// See [`TM1637::try_flush`].
+    ///
+    /// Transmission errors are ignored; the registers that couldn't be
+    /// written are transmitted with the next flush.
+    pub fn flush(&self) {
+        let _ = self.try_flush();
+    }
+
+    /// Writes the raw segments data of the digits beginning at `digit`.
+    /// See [`TM1637::try_write_digits`].
+    ///
+    /// Transmission errors are ignored. Digits that the display module
+    /// doesn't have are skipped.
+    pub fn write_digits(&self, segments: &[u8], digit: u8) {
+        let n = self
+            .board_profile
+            .digits()
+            .saturating_sub(digit as usize)
+            .min(segments.len());
+        let _ = self.try_write_digits(&segments[..n], digit);
+    }
+
+    /// Reads the key-scan data from the TM1637 and decodes it.
+    /// Invalid scan codes (e.g. due to a transmission error) are reported
+    /// as [`KeyCode::NoKey`].
+    ///
+    /// The TM1637 drives DIO during the read. Therefore, `pin_dio_read_fn`
+    /// must switch DIO to input (or read an open-drain pin that is high).
+    pub fn read_keys(&self) -> KeyCode {
+        self.try_read_keys().unwrap_or(KeyCode::NoKey)
+    }
+
+    /// Resynchronizes the bus. See [`TM1637::try_recover_bus`].
+    pub fn recover_bus(&self) {
+        let _ = self.try_recover_bus();
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<E: Debug + 'static, F: Font> TM1637Adapter<E, F> {
+    /// Turns this adapter into one with infallible GPIO functions by
+    /// wrapping them so that they panic on GPIO errors. This way, you can
+    /// use the non-`try_*`-methods with any GPIO backend.
+    pub fn into_unwrapping(self) -> TM1637Adapter<Infallible, F> {
+        let pins = self.pins.into_inner();
+        let pin_clock_write_fn = pins.pin_clock_write_fn;
+        let pin_dio_write_fn = pins.pin_dio_write_fn;
+        let pin_dio_read_fn = pins.pin_dio_read_fn;
+        TM1637 {
+            pins: RefCell::new(CallbackPins {
+                pin_clock_write_fn: Box::from(move |bit| {
+                    pin_clock_write_fn(bit).expect("could not write CLK pin");
+                    Ok(())
+                }),
+                pin_dio_write_fn: Box::from(move |bit| {
+                    pin_dio_write_fn(bit).expect("could not write DIO pin");
+                    Ok(())
+                }),
+                pin_dio_read_fn: Box::from(move || {
+                    Ok(pin_dio_read_fn().expect("could not read DIO pin"))
+                }),
+                bit_delay_fn: pins.bit_delay_fn,
+                delay_ns_fn: pins.delay_ns_fn,
+            }),
+            brightness: self.brightness,
+            ack_policy: self.ack_policy,
+            bus_recovery: self.bus_recovery,
+            timing_profile: self.timing_profile,
+            board_profile: self.board_profile,
+            orientation: self.orientation,
+            font: self.font,
+            frame: self.frame,
+            dirty: self.dirty,
+            committed_brightness: self.committed_brightness,
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<E: 'static, F: Font> TM1637Adapter<E, F> {
+    /// Wraps the GPIO functions of this adapter so that every CLK/DIO write,
+    /// DIO read and bit delay is recorded into the returned [`Trace`].
+    /// The timestamps are taken from `clock_ns`, see [`Trace::new`].
+    pub fn into_recording(self, clock_ns: impl Fn() -> u64 + 'static) -> (Self, Rc<Trace>) {
+        let trace = Rc::new(Trace::new(clock_ns));
+        let pins = self.pins.into_inner();
+        let pin_clock_write_fn = pins.pin_clock_write_fn;
+        let pin_dio_write_fn = pins.pin_dio_write_fn;
+        let pin_dio_read_fn = pins.pin_dio_read_fn;
+        let bit_delay_fn = pins.bit_delay_fn;
+        let delay_ns_fn = pins.delay_ns_fn;
+        let (clk_trace, dio_trace, read_trace) = (trace.clone(), trace.clone(), trace.clone());
+        let (delay_trace, delay_ns_trace) = (trace.clone(), trace.clone());
+        let adapter = Self {
+            pins: RefCell::new(CallbackPins {
+                pin_clock_write_fn: Box::from(move |bit| {
+                    clk_trace.record(clk_trace.now(), PinEventKind::WriteClk(bit));
+                    pin_clock_write_fn(bit)
+                }),
+                pin_dio_write_fn: Box::from(move |bit| {
+                    dio_trace.record(dio_trace.now(), PinEventKind::WriteDio(bit));
+                    pin_dio_write_fn(bit)
+                }),
+                pin_dio_read_fn: Box::from(move || {
+                    let bit = pin_dio_read_fn()?;
+                    read_trace.record(read_trace.now(), PinEventKind::ReadDio(bit));
+                    Ok(bit)
+                }),
+                bit_delay_fn: Box::from(move || {
+                    let start = delay_trace.now();
+                    bit_delay_fn();
+                    let duration_ns = delay_trace.now() - start;
+                    delay_trace.record(start, PinEventKind::BitDelay { duration_ns });
+                }),
+                delay_ns_fn: delay_ns_fn.map(|delay_ns_fn| -> Box<dyn Fn(u32)> {
+                    Box::from(move |ns| {
+                        let start = delay_ns_trace.now();
+                        delay_ns_fn(ns);
+                        let duration_ns = delay_ns_trace.now() - start;
+                        delay_ns_trace.record(start, PinEventKind::BitDelay { duration_ns });
+                    })
+                }),
+            }),
+            ..self
+        };
+        (adapter, trace)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<E> TM1637Adapter<E> {
+    /// Creates a new object to interact via GPIO with a TM1637 whose GPIO
+    /// functions may fail. Activates the display and set's the brightness to
+    /// the highest value.
+    ///
+    /// * `pin_clock_write_fn` function to write bit to CLK pin
+    /// * `pin_dio_write_fn` function to write bit to DIO pin
+    /// * `pin_dio_read_fn` function to read value from DIO pin. It must leave
+    ///   DIO released (input), the driver also calls it to release DIO.
+    /// * `bit_delay_fn` function that is invoked after a bit has been written to a pin.
+    ///   It depends on your hardware and your GPIO driver. Sometimes 0 is even fine.
+    pub fn try_new(
+        pin_clock_write_fn: Box<dyn Fn(GpioPinValue) -> Result<(), E>>,
+        pin_dio_write_fn: Box<dyn Fn(GpioPinValue) -> Result<(), E>>,
+        pin_dio_read_fn: Box<dyn Fn() -> Result<GpioPinValue, E>>,
+        bit_delay_fn: Box<dyn Fn()>,
+    ) -> Result<Self, TM1637Error<E>> {
+        Self::try_new_with_bus_recovery(
             pin_clock_write_fn,
             pin_dio_write_fn,
             pin_dio_read_fn,
             bit_delay_fn,
+            BusRecovery::default(),
+        )
+    }
+
+    /// Like [`TM1637Adapter::try_new`] but with the given [`BusRecovery`].
+    /// With [`BusRecovery::on_init`], the bus is recovered right away.
+    pub fn try_new_with_bus_recovery(
+        pin_clock_write_fn: Box<dyn Fn(GpioPinValue) -> Result<(), E>>,
+        pin_dio_write_fn: Box<dyn Fn(GpioPinValue) -> Result<(), E>>,
+        pin_dio_read_fn: Box<dyn Fn() -> Result<GpioPinValue, E>>,
+        bit_delay_fn: Box<dyn Fn()>,
+        bus_recovery: BusRecovery,
+    ) -> Result<Self, TM1637Error<E>> {
+        let pins = CallbackPins {
+            pin_clock_write_fn,
+            pin_dio_write_fn,
+            pin_dio_read_fn,
+            bit_delay_fn,
+            delay_ns_fn: None,
+        };
+        Self::from_pins_with_bus_recovery(pins, bus_recovery)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<E, F: Font> TM1637Adapter<E, F> {
+    /// Sets the function that waits for the given nanoseconds. It is used
+    /// instead of `bit_delay_fn` once a [`TimingProfile`] is set with
+    /// [`TM1637::set_timing_profile`]. On `std` targets, you can use
+    /// `timing::BusyWait` (feature `std`).
+    pub fn set_delay_ns_fn(&mut self, delay_ns_fn: Box<dyn Fn(u32)>) {
+        self.pins.get_mut().delay_ns_fn = Some(delay_ns_fn);
+    }
+}
+
+impl<P: Tm1637Pins> TM1637<P> {
+    /// Creates a new driver that talks to the TM1637 via the given pins.
+    /// Activates the display and set's the brightness to the highest value.
+    pub fn from_pins(pins: P) -> Result<Self, TM1637Error<P::Error>> {
+        Self::from_pins_with_bus_recovery(pins, BusRecovery::default())
+    }
+
+    /// Like [`TM1637::from_pins`] but with the given [`BusRecovery`]. With
+    /// [`BusRecovery::on_init`], the bus is recovered right away, e.g. because
+    /// a previous process was killed in the middle of a frame.
+    pub fn from_pins_with_bus_recovery(
+        mut pins: P,
+        bus_recovery: BusRecovery,
+    ) -> Result<Self, TM1637Error<P::Error>> {
+        // assume both are already output pins - this is the contract that needs to be fulfilled!
+        pins.write_clk(GpioPinValue::LOW)
+            .map_err(TM1637Error::Pin)?;
+        pins.write_dio(GpioPinValue::LOW)
+            .map_err(TM1637Error::Pin)?;
+
+        let display = Self {
+            pins: RefCell::new(pins),
             brightness: DisplayState::ON as u8 | Brightness::L7 as u8,
+            ack_policy: AckPolicy::default(),
+            bus_recovery,
+            timing_profile: None,
+            board_profile: BoardProfile::default(),
+            orientation: Orientation::NORMAL,
+            font: AsciiFont::DEFAULT,
+            frame: Cell::new([0; DISPLAY_REGISTERS_COUNT]),
+            // the content of the display registers is unknown
+            dirty: Cell::new(ALL_REGISTERS_DIRTY),
+            committed_brightness: Cell::new(None),
+        };
+        if bus_recovery.on_init {
+            display.try_recover_bus()?;
         }
+        Ok(display)
+    }
+}
+
+impl<P: Tm1637Pins, F: Font> TM1637<P, F> {
+    /// Destroys the driver and returns the pins.
+    pub fn release(self) -> P {
+        self.pins.into_inner()
     }
 
     /// Sets the display state. The display state is the 3rd bit of the
@@ -225,6 +742,30 @@ impl TM1637Adapter {
         self.brightness = ds as u8 | old_brightness;
     }
 
+    /// Returns the configured display state. It may not be committed yet.
+    pub const fn display_state(&self) -> DisplayState {
+        if self.brightness & DisplayState::ON as u8 == 0 {
+            DisplayState::OFF
+        } else {
+            DisplayState::ON
+        }
+    }
+
+    /// Returns the configured brightness. It may not be committed yet.
+    pub fn brightness(&self) -> Brightness {
+        Brightness::try_from(self.brightness & 0b0000_0111)
+            .unwrap_or_else(|_| unreachable!("brightness has 3 bits"))
+    }
+
+    /// Returns a snapshot of the raw segments data of all display registers,
+    /// i.e. the data that was written last. This includes data that was
+    /// set in the frame buffer but is not flushed yet.
+    // `Cell::get` is `const` since Rust 1.88, which is above our MSRV
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn segments(&self) -> [u8; DISPLAY_REGISTERS_COUNT] {
+        self.frame.get()
+    }
+
     /// Sets the brightness of the screen. The brightness are the lower
     /// 3 bits of the "display control"-command.
     /// This setting is not committed until a write operation has been made.
@@ -234,307 +775,948 @@ impl TM1637Adapter {
         self.brightness = display_on | brightness as u8;
     }
 
+    /// Sets how acknowledges of the TM1637 are awaited and whether frames
+    /// are retransmitted if the TM1637 doesn't acknowledge them.
+    pub const fn set_ack_policy(&mut self, ack_policy: AckPolicy) {
+        self.ack_policy = ack_policy;
+    }
+
+    /// Sets when the driver resynchronizes the bus on its own.
+    /// [`BusRecovery::on_init`] only has an effect when the driver is created
+    /// with [`TM1637::from_pins_with_bus_recovery`] or
+    /// `TM1637Adapter::try_new_with_bus_recovery`.
+    pub const fn set_bus_recovery(&mut self, bus_recovery: BusRecovery) {
+        self.bus_recovery = bus_recovery;
+    }
+
+    /// Sets how long the driver waits in each phase of the bus signals.
+    /// With `None` (the default), [`Tm1637Pins::bit_delay`] is used for all
+    /// phases. With a profile, [`Tm1637Pins::delay_ns`] is used.
+    pub const fn set_timing_profile(&mut self, timing_profile: Option<TimingProfile>) {
+        self.timing_profile = timing_profile;
+    }
+
+    /// Sets how the digits of the display module are wired to the grids of
+    /// the TM1637. This is used by the `*_digits`-methods.
+    pub const fn set_board_profile(&mut self, board_profile: BoardProfile) {
+        self.board_profile = board_profile;
+    }
+
+    /// Returns how the digits of the display module are wired to the grids.
+    pub const fn board_profile(&self) -> BoardProfile {
+        self.board_profile
+    }
+
+    /// Sets how the display module is mounted, e.g. [`Orientation::UPSIDE_DOWN`].
+    /// All segments data is transformed accordingly before it is transmitted,
+    /// so the `write_*`-methods and [`TM1637::segments`] keep working with the
+    /// data as it is meant to be read. The new orientation isn't shown until
+    /// the registers are written again.
+    pub const fn set_orientation(&mut self, orientation: Orientation) {
+        self.orientation = orientation;
+    }
+
+    /// Returns the driver with the font of the text that is written via the
+    /// driver, i.e. by [`TM1637::encode_text_into`] and the [`DisplayWriter`].
+    /// The default is [`AsciiFont::DEFAULT`]. Use a [`font::GlyphRegistry`]
+    /// to embed icons in the text.
+    pub fn with_font<G: Font>(self, font: G) -> TM1637<P, G> {
+        TM1637 {
+            pins: self.pins,
+            brightness: self.brightness,
+            ack_policy: self.ack_policy,
+            bus_recovery: self.bus_recovery,
+            timing_profile: self.timing_profile,
+            board_profile: self.board_profile,
+            orientation: self.orientation,
+            font,
+            frame: self.frame,
+            dirty: self.dirty,
+            committed_brightness: self.committed_brightness,
+        }
+    }
+
+    /// Returns the font of the text that is written via the driver.
+    pub const fn font(&self) -> &F {
+        &self.font
+    }
+
+    /// Returns the font mutably, e.g. to register glyphs at runtime with
+    /// [`font::GlyphRegistry::insert`].
+    pub const fn font_mut(&mut self) -> &mut F {
+        &mut self.font
+    }
+
+    /// Encodes the text with the font of the driver into `buf`. `'.'` and
+    /// `':'` are folded into the point bit of the preceding character as
+    /// described by the [`BoardProfile`] (see
+    /// [`encoding::encode_string_iter_with_points`]). Characters that don't
+    /// fit into the buffer are skipped.
+    ///
+    /// Returns the number of bytes that were written into `buf`.
+    pub fn encode_text_into(&self, text: &str, buf: &mut [u8]) -> usize {
+        let point_style = self.board_profile.point_style();
+        buf.iter_mut()
+            .zip(encoding::encode_string_iter_with_points_and_font(
+                text,
+                point_style,
+                &self.font,
+            ))
+            .map(|(dst, bits)| *dst = bits)
+            .count()
+    }
+
+    /// Returns a [`core::fmt::Write`] implementation that writes formatted
+    /// text to the display. See [`DisplayWriter`].
+    pub const fn writer(&self) -> DisplayWriter<'_, P, F> {
+        DisplayWriter::new(self)
+    }
+
+    /// Sets the raw segments data of one display register in the frame buffer.
+    /// This setting is not committed until [`TM1637::flush`] has been called.
+    ///
+    /// Fails with [`TM1637Error::InvalidPosition`] if `position` is not
+    /// smaller than [`DISPLAY_REGISTERS_COUNT`].
+    pub fn set_segment(&mut self, segments: u8, position: u8) -> Result<(), TM1637Error<P::Error>> {
+        self.set_segments(&[segments], position)
+    }
+
+    /// Sets the raw segments data of the display registers beginning at `pos`
+    /// in the frame buffer. Only registers whose data really changes are
+    /// transmitted by the next [`TM1637::flush`].
+    ///
+    /// Fails with [`TM1637Error::InvalidPosition`] if the data doesn't fit into
+    /// the [`DISPLAY_REGISTERS_COUNT`] display registers starting at `pos`.
+    pub fn set_segments(&mut self, segments: &[u8], pos: u8) -> Result<(), TM1637Error<P::Error>> {
+        if !protocol::registers_fit(pos, segments.len()) {
+            return Err(TM1637Error::InvalidPosition);
+        }
+        let frame = self.frame.get_mut();
+        let dirty = self.dirty.get_mut();
+        for (i, &byte) in segments.iter().enumerate() {
+            let register = pos as usize + i;
+            if frame[register] != byte {
+                frame[register] = byte;
+                *dirty |= 1 << register;
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets the raw segments data of the digits beginning at `digit` in the
+    /// frame buffer. Unlike [`TM1637::set_segments`], the digits are counted
+    /// from left to right as described by the [`BoardProfile`].
+    ///
+    /// Fails with [`TM1637Error::InvalidPosition`] if the display module
+    /// doesn't have that many digits.
+    pub fn set_digits(&mut self, segments: &[u8], digit: u8) -> Result<(), TM1637Error<P::Error>> {
+        if digit as usize + segments.len() > self.board_profile.digits() {
+            return Err(TM1637Error::InvalidPosition);
+        }
+        for (i, &byte) in segments.iter().enumerate() {
+            let grid = self.grid(digit as usize + i)?;
+            self.set_segment(byte, grid)?;
+        }
+        Ok(())
+    }
+
+    /// Transmits the changes of the frame buffer to the TM1637. Only the
+    /// contiguous range of display registers from the first to the last
+    /// changed one is written. The "display control"-command is only sent
+    /// if the display state or the brightness changed since it was committed
+    /// the last time.
+    ///
+    /// If nothing changed, nothing is transmitted. On errors, the registers
+    /// that couldn't be written stay marked as changed.
+    pub fn try_flush(&self) -> Result<(), TM1637Error<P::Error>> {
+        let dirty = self.dirty.get();
+        if dirty != 0 {
+            let first = dirty.trailing_zeros() as usize;
+            let last = 7 - dirty.leading_zeros() as usize;
+            self.send_registers(first, last)?;
+        }
+        if self.committed_brightness.get() != Some(self.brightness) {
+            self.try_write_display_state()?;
+        }
+        Ok(())
+    }
+
     /// Writes all raw segments data beginning at the position into the display registers.
     /// It uses auto increment internally to write into all further registers.
-    /// This functions does an internal check so that not more than 6 registers can be
-    /// addressed/written.
+    /// Afterwards, the display state is committed as well.
     /// * `segments` Raw data describing the bits of the 7 segment display.
-    /// * `n` Length of segments array.
     /// * `pos` The start position of the display register. While bytes are
     ///   written, address is adjusted internally via auto increment. Usually
     ///   this is 0, if you want to write data to all 7 segment displays.
-    pub fn write_segments_raw(&self, segments: &[u8], pos: u8) {
-        let mut n = segments.len() as u8;
-        // being a little bit more failure tolerant
-        if n == 0 {
-            return;
-        } // nothing to do
-        let pos = pos % DISPLAY_REGISTERS_COUNT as u8; // only valid positions/registers
+    ///
+    /// Fails with [`TM1637Error::InvalidPosition`] if the data doesn't fit into
+    /// the [`DISPLAY_REGISTERS_COUNT`] display registers starting at `pos`.
+    pub fn try_write_segments_raw(
+        &self,
+        segments: &[u8],
+        pos: u8,
+    ) -> Result<(), TM1637Error<P::Error>> {
+        if segments.is_empty() {
+            return Ok(());
+        }
+        if !protocol::registers_fit(pos, segments.len()) {
+            return Err(TM1637Error::InvalidPosition);
+        }
 
-        // valid values are
-        //   n = 1, pos = {0, 1, 2, 3, 4, 5}
-        //   n = 2, pos = {0, 1, 2, 3, 4}
-        //   n = 3, pos = {0, 1, 2, 3}
-        //   n = 4, pos = {0, 1, 2}
-        //   n = 5, pos = {0, 1}
-        //   n = 6, pos = {0}
-        // => n + pos must be <= DISPLAY_REGISTERS_COUNT
+        // keep the frame buffer in sync with what we write
+        let mut frame = self.frame.get();
+        frame[pos as usize..pos as usize + segments.len()].copy_from_slice(segments);
+        self.frame.set(frame);
+        self.send_registers(pos as usize, pos as usize + segments.len() - 1)?;
+
+        // we do this everytime because it will be a common flow that people write something
+        // and expect the display to be on
+        self.try_write_display_state()
+    }
 
-        if n + pos > DISPLAY_REGISTERS_COUNT as u8 {
-            // only write as much data as registers are available
-            n = DISPLAY_REGISTERS_COUNT as u8 - pos;
+    /// Writes the display registers `first` to `last` of the frame buffer to
+    /// the TM1637 and updates which registers are marked as changed.
+    fn send_registers(&self, first: usize, last: usize) -> Result<(), TM1637Error<P::Error>> {
+        if first > last || last >= DISPLAY_REGISTERS_COUNT {
+            return Err(TM1637Error::InvalidPosition);
         }
+        // registers that are written now; if the transmission fails their
+        // content on the TM1637 is unknown
+        let registers = (((1_u16 << (last - first + 1)) - 1) << first) as u8;
+        self.dirty.set(self.dirty.get() | registers);
+
+        // the orientation may spread the registers, then the ones in between
+        // are refreshed as well
+        let oriented = self.oriented_registers(registers);
+        let pos = oriented.trailing_zeros() as usize;
+        let segments = &self.oriented_frame()[pos..=7 - oriented.leading_zeros() as usize];
 
         // Command 1 / 2
         // for more information about this flow: see data sheet / specification of TM1637
         // or AZDelivery's 7 segment display
-        self.start();
-        self.write_byte_and_wait_ack(ISA::DataCommandWriteToDisplay as u8);
-        self.stop();
+        self.send_frame(&Frame::new(&[ISA::DataCommandWriteToDisplay as u8]))?;
 
         // Command 2
-        self.start();
-        self.write_byte_and_wait_ack(ISA::AddressCommandBase as u8 | (pos & 0x3));
-
-        // Write the remaining data bytes
+        // Address command followed by the data bytes.
         // TM1637 does auto increment internally
+        self.send_frame(&Frame::segments(pos as u8, segments))?;
 
-        for i in 0..n {
-            self.write_byte_and_wait_ack(segments[i as usize]);
+        self.dirty.set(self.dirty.get() & !registers);
+        Ok(())
+    }
+
+    /// Returns the display registers of the TM1637 that the given display
+    /// registers of the frame buffer are shown on, see [`TM1637::oriented_frame`].
+    fn oriented_registers(&self, registers: u8) -> u8 {
+        let mut oriented = 0;
+        for register in 0..DISPLAY_REGISTERS_COUNT {
+            let shows = match self.oriented_sources(register) {
+                None => registers & (1 << register) != 0,
+                Some((segments, point)) => {
+                    registers & (1 << segments) != 0
+                        || point.is_some_and(|point| registers & (1 << point) != 0)
+                }
+            };
+            if shows {
+                oriented |= 1 << register;
+            }
         }
-        self.stop();
+        oriented
+    }
 
-        // we do this everytime because it will be a common flow that people write something
-        // and expect the display to be on
-        self.write_display_state();
+    /// Returns the frame buffer transformed by the orientation, i.e. the
+    /// data of the display registers on the TM1637.
+    fn oriented_frame(&self) -> [u8; DISPLAY_REGISTERS_COUNT] {
+        let frame = self.frame.get();
+        let mut oriented = frame;
+        for (register, byte) in oriented.iter_mut().enumerate() {
+            if let Some((segments, point)) = self.oriented_sources(register) {
+                let point_bit = SegmentBits::SegPoint as u8;
+                *byte = self.orientation.transform_segments(frame[segments]) & !point_bit
+                    | point.map_or(0, |point| frame[point] & point_bit);
+            }
+        }
+        oriented
     }
 
-    /// This uses fixed address mode (see data sheet) internally to write data to
-    /// a specific position of the display.
-    /// Position is 0, 1, 2, or 3.
-    pub fn write_segment_raw(&self, segments: u8, position: u8) {
-        self.write_segments_raw(&[segments], position)
+    /// Returns the display registers of the frame buffer whose segments and
+    /// whose point are shown on the given display register of the TM1637,
+    /// or `None` if the register is not transformed.
+    fn oriented_sources(&self, register: usize) -> Option<(usize, Option<usize>)> {
+        let board = self.board_profile;
+        let digit = match board.digit(register as u8) {
+            Some(digit) if self.orientation != Orientation::NORMAL => digit,
+            _ => return None,
+        };
+        let grid = |digit: usize| {
+            board
+                .grid(digit)
+                .map_or_else(|| unreachable!("digit of the board"), usize::from)
+        };
+        let source = if self.orientation.reverse_digits {
+            board.digits() - 1 - digit
+        } else {
+            digit
+        };
+        let point = match board.point_style() {
+            PointStyle::Colon(_) => Some(register),
+            // the point of the previous digit appears in front of this one
+            PointStyle::DecimalPoint if self.orientation.moves_points() => {
+                source.checked_sub(1).map(grid)
+            }
+            PointStyle::DecimalPoint => Some(grid(source)),
+        };
+        Some((grid(source), point))
+    }
+
+    /// Writes the raw segments data of the digits beginning at `digit` into
+    /// the display registers. Unlike [`TM1637::try_write_segments_raw`], the
+    /// digits are counted from left to right as described by the
+    /// [`BoardProfile`]. Grids between the written ones are refreshed with
+    /// the content of the frame buffer.
+    /// Afterwards, the display state is committed as well.
+    ///
+    /// Fails with [`TM1637Error::InvalidPosition`] if the display module
+    /// doesn't have that many digits.
+    pub fn try_write_digits(
+        &self,
+        segments: &[u8],
+        digit: u8,
+    ) -> Result<(), TM1637Error<P::Error>> {
+        if segments.is_empty() {
+            return Ok(());
+        }
+        let mut frame = self.frame.get();
+        let mut registers = 0_u8;
+        for (i, &byte) in segments.iter().enumerate() {
+            let grid = self.grid(digit as usize + i)?;
+            frame[grid as usize] = byte;
+            registers |= 1 << grid;
+        }
+        self.frame.set(frame);
+
+        let first = registers.trailing_zeros() as usize;
+        let last = 7 - registers.leading_zeros() as usize;
+        self.send_registers(first, last)?;
+        self.try_write_display_state()
+    }
+
+    /// Fallible version of [`TM1637::write_segment_raw`].
+    pub fn try_write_segment_raw(
+        &self,
+        segments: u8,
+        position: u8,
+    ) -> Result<(), TM1637Error<P::Error>> {
+        self.try_write_segments_fixed(&[(position, segments)])
+    }
+
+    /// Writes raw segments data to arbitrary positions of the display in
+    /// fixed address mode (see data sheet). All `(position, segments)`-pairs
+    /// are written after one data command. Unlike
+    /// [`TM1637::try_write_segments_raw`], the registers between the positions
+    /// are not touched. This is useful for sparse updates, e.g. if only the
+    /// digit with the colon changes.
+    /// Afterwards, the display state is committed as well.
+    ///
+    /// Fails with [`TM1637Error::InvalidPosition`] if a position is not smaller
+    /// than [`DISPLAY_REGISTERS_COUNT`]. Nothing is transmitted in this case.
+    pub fn try_write_segments_fixed(
+        &self,
+        segments: &[(u8, u8)],
+    ) -> Result<(), TM1637Error<P::Error>> {
+        if segments.is_empty() {
+            return Ok(());
+        }
+        if segments
+            .iter()
+            .any(|&(pos, _)| pos as usize >= DISPLAY_REGISTERS_COUNT)
+        {
+            return Err(TM1637Error::InvalidPosition);
+        }
+
+        // keep the frame buffer in sync with what we write; the registers are
+        // unknown until all of them are written successfully
+        let mut frame = self.frame.get();
+        let mut registers = 0;
+        for &(pos, byte) in segments {
+            frame[pos as usize] = byte;
+            registers |= 1 << pos;
+        }
+        self.frame.set(frame);
+        self.dirty.set(self.dirty.get() | registers);
+
+        // Command 1
+        self.send_frame(&Frame::new(&[ISA::DataCommandFixedAddress as u8]))?;
+        // Command 2 for each register: address command followed by one data byte
+        if self.orientation == Orientation::NORMAL {
+            for &(pos, byte) in segments {
+                self.send_frame(&Frame::segments(pos, &[byte]))?;
+            }
+        } else {
+            let oriented = self.oriented_registers(registers);
+            let frame = self.oriented_frame();
+            for pos in (0..DISPLAY_REGISTERS_COUNT).filter(|pos| oriented & (1 << pos) != 0) {
+                self.send_frame(&Frame::segments(pos as u8, &frame[pos..=pos]))?;
+            }
+        }
+        self.dirty.set(self.dirty.get() & !registers);
+
+        self.try_write_display_state()
     }
 
     /// Send command that sets the display state on the micro controller.
-    pub fn write_display_state(&self) {
-        self.start();
+    pub fn try_write_display_state(&self) -> Result<(), TM1637Error<P::Error>> {
+        self.committed_brightness.set(None);
         // bits 0-2 brightness; bit 3 is on/off
-        self.write_byte_and_wait_ack(ISA::DisplayCommandBase as u8 | self.brightness);
-        self.stop();
+        self.send_frame(&Frame::display_control(self.brightness))?;
+        self.committed_brightness.set(Some(self.brightness));
+        Ok(())
+    }
+
+    /// Checks whether a TM1637 is connected to the pins: the configured
+    /// display state is sent (see [`TM1637::try_write_display_state`]) and
+    /// the TM1637 must acknowledge it. Returns `false` if it doesn't.
+    ///
+    /// DIO needs a pull-up resistor, otherwise a floating line may look like
+    /// an acknowledge.
+    pub fn try_probe(&self) -> Result<bool, TM1637Error<P::Error>> {
+        match self.try_write_display_state() {
+            Ok(()) => Ok(true),
+            Err(TM1637Error::NoAck(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
     }
 
     /// Clears the display.
-    pub fn clear(&self) {
+    pub fn try_clear(&self) -> Result<(), TM1637Error<P::Error>> {
         // begin at position 0 and write 0 into display registers 0 to 5
-        self.write_segments_raw(&[0, 0, 0, 0, 0, 0], 0);
+        self.try_write_segments_raw(&[0; DISPLAY_REGISTERS_COUNT], 0)
+    }
+
+    /// Reads the key-scan data from the TM1637 and decodes it.
+    /// Invalid scan codes are reported as [`TM1637Error::InvalidScanCode`]
+    /// because they indicate a transmission error.
+    ///
+    /// The TM1637 drives DIO during the read. Therefore, [`Tm1637Pins::read_dio`]
+    /// must be able to sense the level of the pin while it is released, see
+    /// [`Tm1637Pins::release_dio`].
+    pub fn try_read_keys(&self) -> Result<KeyCode, TM1637Error<P::Error>> {
+        // the frame always reads a byte
+        let scan_code = self.send_frame(&Frame::READ_KEYS)?.unwrap_or_default();
+        KeyCode::from_scan_code(scan_code).ok_or(TM1637Error::InvalidScanCode(scan_code))
+    }
+
+    /// Resynchronizes the bus, e.g. after a previous process was killed in the
+    /// middle of a frame. DIO is released and CLK clocks out the rest of a
+    /// byte and its ACK (the TM1637 may still be sending key-scan data).
+    /// A stop condition then terminates whatever frame the TM1637 expects.
+    ///
+    /// The dummy bits may have been written into a display register, so all
+    /// registers and the display state are transmitted again by the next
+    /// [`TM1637::flush`].
+    pub fn try_recover_bus(&self) -> Result<(), TM1637Error<P::Error>> {
+        // the TM1637 either receives 1-bits or drives DIO itself
+        self.release_dio()?;
+        for _ in 0..RECOVERY_CLOCKS {
+            self.write_clk(GpioPinValue::LOW)?;
+            self.delay(|t| t.clk_low_ns);
+            self.write_clk(GpioPinValue::HIGH)?;
+            self.delay(|t| t.clk_high_ns);
+        }
+        // DIO may only fall while CLK is low, otherwise this was a start condition
+        self.write_clk(GpioPinValue::LOW)?;
+        self.delay(|t| t.clk_low_ns);
+        self.stop()?;
+
+        self.dirty.set(ALL_REGISTERS_DIRTY);
+        self.committed_brightness.set(None);
+        Ok(())
+    }
+
+    /// Returns the grid of the digit according to the [`BoardProfile`].
+    fn grid(&self, digit: usize) -> Result<u8, TM1637Error<P::Error>> {
+        self.board_profile
+            .grid(digit)
+            .ok_or(TM1637Error::InvalidPosition)
+    }
+
+    /// Transmits a frame and repeats it if the TM1637 didn't acknowledge it,
+    /// until [`AckPolicy::retransmits`] is exhausted. GPIO errors are not
+    /// retried. Returns the byte that the TM1637 sent if the frame reads one.
+    fn send_frame(&self, frame: &Frame) -> Result<Option<u8>, TM1637Error<P::Error>> {
+        let mut retransmits = Retransmits::new(&self.ack_policy);
+        loop {
+            let res = self.transmit_frame(frame);
+            if self.bus_recovery.after_nack && matches!(res, Err(TM1637Error::NoAck(_))) {
+                self.try_recover_bus()?;
+            }
+            if !retransmits.retry(&res) {
+                return res;
+            }
+        }
+    }
+
+    /// Transmits a frame once, enclosed by a start and a stop condition.
+    fn transmit_frame(&self, frame: &Frame) -> Result<Option<u8>, TM1637Error<P::Error>> {
+        self.start()?;
+        let res = frame
+            .bytes()
+            .iter()
+            .try_for_each(|&byte| self.write_byte_and_wait_ack(byte))
+            .and_then(|()| {
+                frame
+                    .reads_byte()
+                    .then(|| self.read_byte_and_wait_ack())
+                    .transpose()
+            });
+        // always finish the frame, even on errors, so that the bus is idle again
+        self.stop()?;
+        res
     }
 
     /// Writes a byte bit by bit and waits for the acknowledge.
-    fn write_byte_and_wait_ack(&self, byte: u8) {
+    fn write_byte_and_wait_ack(&self, byte: u8) -> Result<(), TM1637Error<P::Error>> {
         let mut data = byte;
 
         // 8 bits
         for _ in 0..8 {
             // CLK low
-            (self.pin_clock_write_fn)(GpioPinValue::LOW);
+            self.write_clk(GpioPinValue::LOW)?;
             // Set data bit (we send one bit of our byte per iteration)
             // LSF (least significant bit) first
             // => target device uses a shift register => this way the byte has the
             //    correct order on the target
-            (self.pin_dio_write_fn)(GpioPinValue::from(data & 0x01));
-            self.bit_delay();
+            self.write_dio(GpioPinValue::from(data & 0x01))?;
+            self.delay(|t| t.clk_low_ns);
 
             // CLK high
-            (self.pin_clock_write_fn)(GpioPinValue::HIGH);
-            self.bit_delay();
+            self.write_clk(GpioPinValue::HIGH)?;
+            self.delay(|t| t.clk_high_ns);
 
             // shift to next bit
             data >>= 1;
         }
 
-        self.recv_ack();
+        if self.recv_ack()? {
+            Ok(())
+        } else {
+            Err(TM1637Error::NoAck(byte))
+        }
     }
 
-    /// Encodes a number from 0 to 9999 on the display.
-    pub fn encode_number(num: u16) -> [u8; 4] {
-        let mut num = num % 10000;
-        let mut bits: [u8; 4] = [0; 4];
-        for i in 0..4 {
-            let digit = (num % 10) as u8;
-            bits[3 - i] = Self::encode_digit(digit);
-            num /= 10;
+    /// Reads a byte bit by bit from the TM1637 and waits for the acknowledge.
+    fn read_byte_and_wait_ack(&self) -> Result<u8, TM1637Error<P::Error>> {
+        let mut data = 0;
+
+        // 8 bits
+        for i in 0..8 {
+            // CLK low; TM1637 puts the next bit on DIO
+            self.write_clk(GpioPinValue::LOW)?;
+            // release DIO so that the TM1637 can pull it low
+            self.release_dio()?;
+            self.delay(|t| t.clk_low_ns);
+
+            // CLK high; bit is stable now
+            self.write_clk(GpioPinValue::HIGH)?;
+            self.delay(|t| t.clk_high_ns);
+
+            // LSB (least significant bit) first
+            data |= (self.read_dio()? as u8) << i;
         }
-        bits
-    }
 
-    /// Encodes a number/digit from 0 to 9 to it's bit representation on the display.
-    /// This is not the char (ASCII) representation. It's a number/integer.
-    pub const fn encode_digit(digit: u8) -> u8 {
-        let digit = digit % 10;
-        if digit == 0 {
-            NumCharBits::Zero as u8
-        } else if digit == 1 {
-            NumCharBits::One as u8
-        } else if digit == 2 {
-            NumCharBits::Two as u8
-        } else if digit == 3 {
-            NumCharBits::Three as u8
-        } else if digit == 4 {
-            NumCharBits::Four as u8
-        } else if digit == 5 {
-            NumCharBits::Five as u8
-        } else if digit == 6 {
-            NumCharBits::Six as u8
-        } else if digit == 7 {
-            NumCharBits::Seven as u8
-        } else if digit == 8 {
-            NumCharBits::Eight as u8
-        }
-        // else if digit == 9 { NumCharBits::Nine as u8 }
-        else {
-            NumCharBits::Nine as u8
-        }
-    }
-
-    /// Encodes a char for the 7-segment display. Unknown chars will be a zero byte (space).
-    /// Uses `mappings::UpCharBits` and `mappings::LoCharBits` for the chars. Since there is
-    /// no representation for every char in each case (lower, upper) there will be an replacement
-    /// for lowercase charts by their uppercase counterpart and vice versa.
-    #[allow(clippy::cognitive_complexity)]
-    #[allow(clippy::if_same_then_else)]
-    #[rustfmt::skip]
-    pub const fn encode_char(c: char) -> u8 {
-        // nums
-        if c == '0' { NumCharBits::Zero as u8 }
-        else if c == '1' { NumCharBits::One as u8 }
-        else if c == '2' { NumCharBits::Two as u8 }
-        else if c == '3' { NumCharBits::Three as u8 }
-        else if c == '4' { NumCharBits::Four as u8 }
-        else if c == '5' { NumCharBits::Five as u8 }
-        else if c == '6' { NumCharBits::Six as u8 }
-        else if c == '7' { NumCharBits::Seven as u8 }
-        else if c == '8' { NumCharBits::Eight as u8 }
-        else if c == '9' { NumCharBits::Nine as u8 }
-
-        // latin chars
-        // we map as accurate as possible,
-        // e.g.: a => lowercase a, A => uppercase A
-        //
-        // but in cases where we only have on mapping available
-        // like: b => lowercase b, B => undefined
-        // we map B => lowercase b
-        else if c == 'A' { UpCharBits::UpA as u8 }
-        else if c == 'a' { LoCharBits::LoA as u8 }
-        else if c == 'B' { LoCharBits::LoB as u8 }
-        else if c == 'b' { LoCharBits::LoB as u8 }
-        else if c == 'C' { UpCharBits::UpC as u8 }
-        else if c == 'c' { UpCharBits::UpC as u8 }
-        else if c == 'D' { LoCharBits::LoD as u8 }
-        else if c == 'd' { LoCharBits::LoD as u8 }
-        else if c == 'E' { UpCharBits::UpE as u8 }
-        else if c == 'e' { UpCharBits::UpE as u8 }
-        else if c == 'F' { UpCharBits::UpF as u8 }
-        else if c == 'f' { UpCharBits::UpF as u8 }
-        else if c == 'G' { UpCharBits::UpG as u8 }
-        else if c == 'g' { UpCharBits::UpG as u8 }
-        else if c == 'H' { UpCharBits::UpH as u8 }
-        else if c == 'h' { LoCharBits::LoH as u8 }
-        else if c == 'I' { UpCharBits::UpI as u8 }
-        else if c == 'i' { UpCharBits::UpI as u8 }
-        else if c == 'J' { UpCharBits::UpJ as u8 }
-        else if c == 'j' { UpCharBits::UpJ as u8 }
-        else if c == 'L' { UpCharBits::UpL as u8 }
-        else if c == 'l' { UpCharBits::UpL as u8 }
-        else if c == 'N' { LoCharBits::LoN as u8 }
-        else if c == 'n' { LoCharBits::LoN as u8 }
-        else if c == 'O' { UpCharBits::UpO as u8 }
-        else if c == 'o' { LoCharBits::LoO as u8 }
-        else if c == 'P' { UpCharBits::UpP as u8 }
-        else if c == 'p' { UpCharBits::UpP as u8 }
-        else if c == 'Q' { LoCharBits::LoQ as u8 }
-        else if c == 'q' { LoCharBits::LoQ as u8 }
-        else if c == 'R' { LoCharBits::LoR as u8 }
-        else if c == 'r' { LoCharBits::LoR as u8 }
-        else if c == 'S' { UpCharBits::UpS as u8 }
-        else if c == 's' { UpCharBits::UpS as u8 }
-        else if c == 'T' { LoCharBits::LoT as u8 }
-        else if c == 't' { LoCharBits::LoT as u8 }
-        else if c == 'U' { UpCharBits::UpU as u8 }
-        else if c == 'u' { LoCharBits::LoU as u8 }
-        else if c == 'Y' { LoCharBits::LoY as u8 }
-        else if c == 'y' { LoCharBits::LoY as u8 }
-
-        // special chars
-        else if c == ' ' { SpecialCharBits::Space as u8 }
-        else if c == '?' { SpecialCharBits::QuestionMark as u8 }
-        else if c == '-' { SpecialCharBits::Minus as u8 }
-        else if c == '_' { SpecialCharBits::Underscore as u8 }
-        else if c == '=' { SpecialCharBits::Equals as u8 }
-        else if c == '.' { SpecialCharBits::Dot as u8 }
-        else { SpecialCharBits::Space as u8 }
-    }
-
-    /// Encodes a string for the 7-segment display. This uses
-    /// `encode_char` for each character.
-    pub fn encode_string(str: &str) -> Vec<u8> {
-        str.chars().map(Self::encode_char).collect()
+        // ACK cycle; we don't insist on an acknowledge for data that the
+        // TM1637 sent itself
+        self.recv_ack()?;
+        Ok(data)
     }
 
     /// This tells the TM1637 that data input starts.
     /// This information stands in the official data sheet.
     #[inline]
-    fn start(&self) {
-        (self.pin_dio_write_fn)(GpioPinValue::HIGH);
-        (self.pin_clock_write_fn)(GpioPinValue::HIGH);
-        self.bit_delay();
-        (self.pin_dio_write_fn)(GpioPinValue::LOW);
-        self.bit_delay();
+    fn start(&self) -> Result<(), TM1637Error<P::Error>> {
+        self.write_dio(GpioPinValue::HIGH)?;
+        self.write_clk(GpioPinValue::HIGH)?;
+        self.delay(|t| t.setup_ns);
+        self.write_dio(GpioPinValue::LOW)?;
+        self.delay(|t| t.hold_ns);
 
         // transition from high to low on DIO while CLK is high
         // means: data starts at next clock
+        Ok(())
     }
 
     /// This tells the TM1637 that data input stops.
     /// This information stands in the official data sheet.
     #[inline]
-    fn stop(&self) {
-        (self.pin_dio_write_fn)(GpioPinValue::LOW);
-        (self.pin_clock_write_fn)(GpioPinValue::HIGH);
-        self.bit_delay();
-        (self.pin_dio_write_fn)(GpioPinValue::HIGH);
-        self.bit_delay();
-    }
-
-    /// Receives one acknowledgment after a byte was sent.
-    fn recv_ack(&self) {
-        (self.pin_clock_write_fn)(GpioPinValue::LOW);
-        (self.pin_dio_write_fn)(GpioPinValue::LOW);
-        self.bit_delay();
-        (self.pin_clock_write_fn)(GpioPinValue::HIGH);
-
-        let ack: GpioPinValue = (self.pin_dio_read_fn)();
-
-        // wait a few cycles for ACK to be more fail safe
-        for _ in 0..10 {
-            if ack as u8 == 0 {
-                break;
-            } else {
-                // ACK should be one clock with zero on data lane
+    fn stop(&self) -> Result<(), TM1637Error<P::Error>> {
+        self.write_dio(GpioPinValue::LOW)?;
+        self.write_clk(GpioPinValue::HIGH)?;
+        self.delay(|t| t.setup_ns);
+        self.write_dio(GpioPinValue::HIGH)?;
+        self.delay(|t| t.hold_ns);
+        Ok(())
+    }
+
+    /// Receives one acknowledgment after a byte was sent. Returns whether
+    /// the TM1637 acknowledged the byte.
+    ///
+    /// The TM1637 acknowledges by pulling DIO low from the falling edge of
+    /// the 8th clock until the falling edge of the 9th clock. We release DIO
+    /// and sample it up to [`AckPolicy::timeout`] times while the 9th clock
+    /// is high.
+    fn recv_ack(&self) -> Result<bool, TM1637Error<P::Error>> {
+        self.write_clk(GpioPinValue::LOW)?;
+        // release DIO so that the TM1637 can pull it low
+        self.release_dio()?;
+        self.delay(|t| t.clk_low_ns);
+        self.write_clk(GpioPinValue::HIGH)?;
 
-                // not possible with no_std; TODO provide debug function
-                // eprintln!("ack is not 0! Probably not a problem, tho.")
+        let mut ack = false;
+        for _ in 0..self.ack_policy.samples() {
+            // ACK should be one clock with zero on data lane
+            ack = matches!(self.read_dio()?, GpioPinValue::LOW);
+            // also after the ACK: CLK must stay high for at least one bit delay
+            self.delay(|t| t.ack_wait_ns);
+            if ack {
+                break;
             }
         }
 
-        (self.pin_clock_write_fn)(GpioPinValue::LOW);
-        (self.pin_dio_write_fn)(GpioPinValue::LOW);
-        self.bit_delay();
+        self.write_clk(GpioPinValue::LOW)?;
+        self.write_dio(GpioPinValue::LOW)?;
+        self.delay(|t| t.clk_low_ns);
+
+        Ok(ack)
+    }
+
+    /// Writes the value to the CLK pin.
+    #[inline]
+    fn write_clk(&self, value: GpioPinValue) -> Result<(), TM1637Error<P::Error>> {
+        self.pins
+            .borrow_mut()
+            .write_clk(value)
+            .map_err(TM1637Error::Pin)
+    }
+
+    /// Writes the value to the DIO pin.
+    #[inline]
+    fn write_dio(&self, value: GpioPinValue) -> Result<(), TM1637Error<P::Error>> {
+        self.pins
+            .borrow_mut()
+            .write_dio(value)
+            .map_err(TM1637Error::Pin)
+    }
+
+    /// Releases the DIO pin so that the TM1637 can drive it.
+    #[inline]
+    fn release_dio(&self) -> Result<(), TM1637Error<P::Error>> {
+        self.pins
+            .borrow_mut()
+            .release_dio()
+            .map_err(TM1637Error::Pin)
+    }
+
+    /// Reads the value of the DIO pin.
+    #[inline]
+    fn read_dio(&self) -> Result<GpioPinValue, TM1637Error<P::Error>> {
+        self.pins.borrow_mut().read_dio().map_err(TM1637Error::Pin)
     }
 
-    /// Let the current thread sleep for the configured amount of µs.
-    /// This is necessary so that changed values on the pins (High, Low)
-    /// are applied. The best value here depends on your platform.
-    /// 100µs on Raspberry Pi with GPIO-Pins seems perfectly fine.
+    /// Waits after a bit has been written to a pin. This is necessary so
+    /// that changed values on the pins (High, Low) are applied. Without a
+    /// [`TimingProfile`], this is the bit delay of the pins. Otherwise, `phase`
+    /// selects the duration from the profile.
     #[inline]
-    fn bit_delay(&self) {
-        (self.bit_delay_fn)()
+    fn delay(&self, phase: fn(&TimingProfile) -> u32) {
+        let mut pins = self.pins.borrow_mut();
+        match &self.timing_profile {
+            None => pins.bit_delay(),
+            Some(profile) => pins.delay_ns(phase(profile)),
+        }
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "alloc"))]
 mod tests {
     use super::*;
+    use alloc::rc::Rc;
+    use alloc::vec::Vec;
 
     #[test]
-    fn test_encode_number() {
-        let f = TM1637Adapter::encode_digit;
-        assert_eq!([f(1), f(2), f(3), f(4)], TM1637Adapter::encode_number(1234));
-        assert_eq!([f(9), f(9), f(9), f(9)], TM1637Adapter::encode_number(9999));
+    fn test_fallible_api() {
+        // TM1637 never answers: DIO stays high
+        let adapter = TM1637Adapter::<()>::try_new(
+            Box::from(|_| Ok(())),
+            Box::from(|_| Ok(())),
+            Box::from(|| Ok(GpioPinValue::HIGH)),
+            Box::from(|| {}),
+        )
+        .unwrap();
+        assert_eq!(Err(TM1637Error::NoAck(0x40)), adapter.try_clear());
+        assert_eq!(
+            Err(TM1637Error::InvalidPosition),
+            adapter.try_write_segments_raw(&[0; 4], 3)
+        );
+
+        // GPIO errors are propagated
+        let adapter = TM1637Adapter::try_new(
+            Box::from(|_| Err("clk")),
+            Box::from(|_| Ok(())),
+            Box::from(|| Ok(GpioPinValue::LOW)),
+            Box::from(|| {}),
+        );
+        assert_eq!(Some(TM1637Error::Pin("clk")), adapter.err());
+    }
+
+    #[test]
+    fn test_ack_policy() {
+        // TM1637 doesn't acknowledge the first frame; DIO is sampled while
+        // CLK is high, the other reads release DIO
+        let clk = Rc::new(Cell::new(GpioPinValue::LOW));
+        let clk_ = clk.clone();
+        let reads = Rc::new(Cell::new(0));
+        let reads_ = reads.clone();
+        let mut adapter = TM1637Adapter::<()>::try_new(
+            Box::from(move |v| {
+                clk_.set(v);
+                Ok(())
+            }),
+            Box::from(|_| Ok(())),
+            Box::from(move || {
+                if clk.get() == GpioPinValue::HIGH {
+                    reads_.set(reads_.get() + 1);
+                }
+                Ok(GpioPinValue::from(u8::from(reads_.get() <= 3)))
+            }),
+            Box::from(|| {}),
+        )
+        .unwrap();
+        adapter.set_ack_policy(AckPolicy {
+            timeout: 3,
+            retransmits: 1,
+        });
+        assert_eq!(Ok(()), adapter.try_write_display_state());
+        // 3 samples for the NACK, 1 for the ACK of the retransmitted frame
+        assert_eq!(4, reads.get());
+    }
+
+    #[test]
+    fn test_probe() {
+        let connected = TM1637Adapter::new(
+            Box::from(|_| {}),
+            Box::from(|_| {}),
+            Box::from(|| GpioPinValue::LOW),
+            Box::from(|| {}),
+        );
+        assert!(connected.probe());
+
+        // only the pull-up resistor answers
+        let disconnected = TM1637Adapter::new(
+            Box::from(|_| {}),
+            Box::from(|_| {}),
+            Box::from(|| GpioPinValue::HIGH),
+            Box::from(|| {}),
+        );
+        assert_eq!(Ok(false), disconnected.try_probe());
+    }
+
+    #[test]
+    fn test_bus_recovery_after_nack() {
+        // counts how often CLK is set high; the TM1637 never acknowledges
+        let clocks = Rc::new(Cell::new(0));
+        let clocks_ = clocks.clone();
+        let mut adapter = TM1637Adapter::<()>::try_new(
+            Box::from(move |bit| {
+                clocks_.set(clocks_.get() + u32::from(bit == GpioPinValue::HIGH));
+                Ok(())
+            }),
+            Box::from(|_| Ok(())),
+            Box::from(|| Ok(GpioPinValue::HIGH)),
+            Box::from(|| {}),
+        )
+        .unwrap();
+        assert_eq!(
+            Err(TM1637Error::NoAck(0x8f)),
+            adapter.try_write_display_state()
+        );
+        let without_recovery = clocks.replace(0);
+
+        adapter.set_bus_recovery(BusRecovery {
+            on_init: false,
+            after_nack: true,
+        });
+        assert_eq!(
+            Err(TM1637Error::NoAck(0x8f)),
+            adapter.try_write_display_state()
+        );
+        // 9 dummy clocks and the stop condition
+        assert_eq!(without_recovery + 10, clocks.get());
+    }
+
+    #[test]
+    fn test_flush() {
+        // the TM1637 acknowledges every byte with the first sample; therefore
+        // the number of reads while CLK is high is the number of transmitted
+        // bytes (the other reads release DIO)
+        let clk = Rc::new(Cell::new(GpioPinValue::LOW));
+        let clk_ = clk.clone();
+        let reads = Rc::new(Cell::new(0));
+        let reads_ = reads.clone();
+        let mut adapter = TM1637Adapter::new(
+            Box::from(move |v| clk_.set(v)),
+            Box::from(|_| {}),
+            Box::from(move || {
+                if clk.get() == GpioPinValue::HIGH {
+                    reads_.set(reads_.get() + 1);
+                }
+                GpioPinValue::LOW
+            }),
+            Box::from(|| {}),
+        );
+
+        // initially everything is unknown: data command, address command + 6
+        // registers and display control command
+        adapter.flush();
+        assert_eq!(9, reads.replace(0));
+        adapter.flush();
+        assert_eq!(0, reads.replace(0));
+
+        adapter.set_segment(0x3f, 2).unwrap();
+        adapter.flush();
+        // data command, address command and one register
+        assert_eq!(3, reads.replace(0));
+
+        // unchanged data is not transmitted
+        adapter.set_segments(&[0, 0x3f], 1).unwrap();
+        adapter.flush();
+        assert_eq!(0, reads.replace(0));
+
+        // registers 1 to 3 and the display control command
+        adapter.set_segment(0x06, 1).unwrap();
+        adapter.set_segment(0x06, 3).unwrap();
+        adapter.set_brightness(Brightness::L3);
+        adapter.flush();
+        assert_eq!(6, reads.replace(0));
+
+        assert_eq!(
+            Err(TM1637Error::InvalidPosition),
+            adapter.set_segments(&[0; 2], 5)
+        );
+    }
+
+    /// Adapter whose pins decode the transmitted frames. A frame is the list
+    /// of bytes between a start and a stop condition.
+    fn frame_decoding_adapter() -> (TM1637Adapter, Rc<RefCell<Vec<Vec<u8>>>>) {
+        // samples DIO at each rising CLK edge between start and stop condition
+        #[derive(Default)]
+        struct Bus {
+            clk: u8,
+            dio: u8,
+            frames: Vec<Vec<u8>>,
+        }
+        let bus = Rc::new(RefCell::new(Bus::default()));
+        let bus_clk = bus.clone();
+        let frames = Rc::new(RefCell::new(Vec::new()));
+        let frames_ = frames.clone();
+        let adapter = TM1637Adapter::new(
+            Box::from(move |v| {
+                let mut bus = bus_clk.borrow_mut();
+                if bus.clk == 0 && v as u8 == 1 {
+                    let dio = bus.dio;
+                    if let Some(frame) = bus.frames.last_mut() {
+                        frame.push(dio);
+                    }
+                }
+                bus.clk = v as u8;
+            }),
+            Box::from(move |v| {
+                let mut bus = bus.borrow_mut();
+                if bus.clk == 1 && (v as u8) < bus.dio {
+                    // start condition
+                    bus.frames.push(Vec::new());
+                } else if bus.clk == 1 && (v as u8) > bus.dio {
+                    // stop condition; CLK went high for it
+                    let mut bits = bus.frames.pop().unwrap();
+                    bits.pop();
+                    // 8 data bits + 1 ACK bit per byte
+                    let bytes = bits
+                        .chunks(9)
+                        .map(|bits| (0..8).fold(0, |byte, i| byte | bits[i] << i))
+                        .collect();
+                    frames_.borrow_mut().push(bytes);
+                }
+                bus.dio = v as u8;
+            }),
+            Box::from(|| GpioPinValue::LOW),
+            Box::from(|| {}),
+        );
+        (adapter, frames)
+    }
+
+    #[test]
+    fn test_read_back() {
+        let (mut adapter, _) = frame_decoding_adapter();
+        assert_eq!(DisplayState::ON, adapter.display_state());
+        assert_eq!(Brightness::L7, adapter.brightness());
+
+        adapter.set_brightness(Brightness::try_from(u8::from(adapter.brightness()) - 2).unwrap());
+        adapter.set_display_state(DisplayState::OFF);
+        assert_eq!(DisplayState::OFF, adapter.display_state());
+        assert_eq!(Brightness::L5, adapter.brightness());
+
+        adapter.write_segments_raw(&[1, 2], 1);
+        adapter.set_segment(3, 5).unwrap();
+        assert_eq!([0, 1, 2, 0, 0, 3], adapter.segments());
+
+        assert_eq!(Err(InvalidValueError(8)), Brightness::try_from(8));
+        assert_eq!(Ok(DisplayState::ON), DisplayState::try_from(0b1000));
+        assert_eq!(Err(InvalidValueError(1)), DisplayState::try_from(1));
+    }
+
+    #[test]
+    fn test_fixed_address_mode() {
+        let (adapter, frames) = frame_decoding_adapter();
+        adapter.write_segments_fixed(&[(5, 0x3f), (1, 0x86)]);
+        assert_eq!(
+            [&[0x44][..], &[0xc5, 0x3f], &[0xc1, 0x86], &[0x8f]].as_slice(),
+            frames.borrow().as_slice()
+        );
+
+        assert_eq!(
+            Err(TM1637Error::InvalidPosition),
+            adapter.try_write_segments_fixed(&[(0, 0), (6, 0)])
+        );
+    }
+
+    #[test]
+    fn test_board_profile() {
+        let (mut adapter, frames) = frame_decoding_adapter();
+
+        // all 6 grids are addressable
+        adapter.write_segments_raw(&[0x06, 0x5b], 4);
+        assert_eq!(&[0xc4, 0x06, 0x5b], frames.borrow()[1].as_slice());
+        frames.borrow_mut().clear();
+
+        adapter.set_board_profile(BoardProfile::SIX_DIGIT);
+        adapter.write_digits(&[1, 2, 3, 4, 5, 6], 0);
+        assert_eq!(&[0xc0, 3, 2, 1, 6, 5, 4], frames.borrow()[1].as_slice());
+        frames.borrow_mut().clear();
+
+        // digits 2 and 3 are the grids 0 and 5; the grids in between are
+        // refreshed from the frame buffer
+        adapter.write_digits(&[7, 8], 2);
+        assert_eq!(&[0xc0, 7, 2, 1, 6, 5, 8], frames.borrow()[1].as_slice());
+
+        assert_eq!(
+            Err(TM1637Error::InvalidPosition),
+            adapter.try_write_digits(&[0; 2], 5)
+        );
         assert_eq!(
-            [f(0), f(0), f(0), f(0)],
-            TM1637Adapter::encode_number(10000)
+            Err(TM1637Error::InvalidPosition),
+            adapter.set_digits(&[0; 2], 5)
         );
-        assert_eq!([f(7), f(6), f(5), f(4)], TM1637Adapter::encode_number(7654));
+        adapter.set_digits(&[9], 5).unwrap();
+        assert_eq!(Ok(()), adapter.try_flush());
+        assert_eq!(&[0xc3, 9], frames.borrow()[4].as_slice());
     }
 }
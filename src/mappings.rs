@@ -156,4 +156,9 @@ pub enum SpecialCharBits {
         | SegmentBits::SegE as u8,
     /// Dot (.).
     Dot = SegmentBits::SegPoint as u8,
+    /// Degree sign (°).
+    Degree = SegmentBits::SegA as u8
+        | SegmentBits::SegB as u8
+        | SegmentBits::SegF as u8
+        | SegmentBits::SegG as u8,
 }
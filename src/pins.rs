@@ -0,0 +1,136 @@
+// MIT License
+//
+// Copyright (c) 2022 Philipp Schuster <phip1611@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Abstraction over the GPIO pins that are connected to the TM1637.
+
+use crate::GpioPinValue;
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+#[cfg(feature = "alloc")]
+use core::fmt::{Debug, Formatter};
+
+/// Access to the CLK and DIO pins of a TM1637 and to a delay function.
+///
+/// Implement this trait to connect [`crate::TM1637`] to your GPIO
+/// interface/library. The implementation must ensure that:
+/// - CLK and DIO are output pins when the driver is created,
+/// - [`Tm1637Pins::release_dio`] stops driving DIO, i.e. DIO is switched to
+///   input or is an open-drain pin that is set high. The TM1637 pulls DIO low
+///   for the ACK and the key-scan data; a push-pull pin that drives it high
+///   then works against the chip,
+/// - [`Tm1637Pins::read_dio`] senses the level of DIO while it is released.
+pub trait Tm1637Pins {
+    /// Error type of the GPIO interface.
+    type Error;
+
+    /// Writes the value to the CLK pin.
+    fn write_clk(&mut self, value: GpioPinValue) -> Result<(), Self::Error>;
+
+    /// Writes the value to the DIO pin.
+    fn write_dio(&mut self, value: GpioPinValue) -> Result<(), Self::Error>;
+
+    /// Releases the DIO pin so that the TM1637 can drive it. The line is
+    /// pulled high by the pull-up resistor unless the TM1637 pulls it low.
+    fn release_dio(&mut self) -> Result<(), Self::Error>;
+
+    /// Reads the value of the DIO pin.
+    fn read_dio(&mut self) -> Result<GpioPinValue, Self::Error>;
+
+    /// Waits after a bit has been written to a pin. This is necessary so that
+    /// changed values on the pins are applied.
+    fn bit_delay(&mut self);
+
+    /// Waits for the given time. This is used instead of
+    /// [`Tm1637Pins::bit_delay`] if the driver has a [`crate::timing::TimingProfile`].
+    ///
+    /// The default implementation ignores the duration and calls
+    /// [`Tm1637Pins::bit_delay`].
+    fn delay_ns(&mut self, ns: u32) {
+        let _ = ns;
+        self.bit_delay()
+    }
+}
+
+/// [`Tm1637Pins`] that are backed by functions/closures. This is what
+/// [`crate::TM1637Adapter`] uses.
+#[cfg(feature = "alloc")]
+pub struct CallbackPins<E> {
+    /// Function that writes the value on the GPIO pin that acts as the clock.
+    pub(crate) pin_clock_write_fn: Box<dyn Fn(GpioPinValue) -> Result<(), E>>,
+    /// Function that writes the value on the GPIO pin that acts as data in and out.
+    pub(crate) pin_dio_write_fn: Box<dyn Fn(GpioPinValue) -> Result<(), E>>,
+    /// Function that reads from the data in and out pin. It leaves the pin
+    /// released, which is also how DIO is released.
+    pub(crate) pin_dio_read_fn: Box<dyn Fn() -> Result<GpioPinValue, E>>,
+    /// Delay function after data bits and clock bits have been set. This may be necessary
+    /// on some hardware.
+    pub(crate) bit_delay_fn: Box<dyn Fn()>,
+    /// Function that waits for the given nanoseconds. Used with a
+    /// [`crate::timing::TimingProfile`]; if `None`, `bit_delay_fn` is used instead.
+    pub(crate) delay_ns_fn: Option<Box<dyn Fn(u32)>>,
+}
+
+#[cfg(feature = "alloc")]
+impl<E> Debug for CallbackPins<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("CallbackPins")
+            .field("pin_clock_write_fn", &"<func>")
+            .field("pin_dio_write_fn", &"<func>")
+            .field("pin_dio_read_fn", &"<func>")
+            .field("bit_delay_fn", &"<func>")
+            .field("delay_ns_fn", &self.delay_ns_fn.as_ref().map(|_| "<func>"))
+            .finish()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<E> Tm1637Pins for CallbackPins<E> {
+    type Error = E;
+
+    fn write_clk(&mut self, value: GpioPinValue) -> Result<(), E> {
+        (self.pin_clock_write_fn)(value)
+    }
+
+    fn write_dio(&mut self, value: GpioPinValue) -> Result<(), E> {
+        (self.pin_dio_write_fn)(value)
+    }
+
+    fn release_dio(&mut self) -> Result<(), E> {
+        // the read function switches DIO to input
+        (self.pin_dio_read_fn)().map(|_| ())
+    }
+
+    fn read_dio(&mut self) -> Result<GpioPinValue, E> {
+        (self.pin_dio_read_fn)()
+    }
+
+    fn bit_delay(&mut self) {
+        (self.bit_delay_fn)()
+    }
+
+    fn delay_ns(&mut self, ns: u32) {
+        match &self.delay_ns_fn {
+            Some(delay_ns_fn) => delay_ns_fn(ns),
+            None => (self.bit_delay_fn)(),
+        }
+    }
+}
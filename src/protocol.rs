@@ -0,0 +1,156 @@
+// MIT License
+//
+// Copyright (c) 2022 Philipp Schuster <phip1611@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Parts of the bus protocol that don't depend on how the pins are driven.
+//!
+//! [`crate::TM1637`] and the async driver build their frames with [`Frame`]
+//! and decide about retransmissions with [`Retransmits`]. Only clocking the
+//! bits out (blocking or async) is up to the drivers.
+
+use crate::{AckPolicy, DISPLAY_REGISTERS_COUNT, ISA, TM1637Error};
+
+/// A frame on the bus: the bytes between a start and a stop condition,
+/// optionally followed by a byte that the TM1637 sends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Frame {
+    bytes: [u8; DISPLAY_REGISTERS_COUNT + 1],
+    len: u8,
+    /// Whether a byte is read from the TM1637 after the bytes were written.
+    reads_byte: bool,
+}
+
+impl Frame {
+    /// The "data command" that reads the key-scan data, followed by the read
+    /// of the scan code.
+    pub(crate) const READ_KEYS: Self = Self {
+        bytes: [ISA::DataCommandReadKeys as u8, 0, 0, 0, 0, 0, 0],
+        len: 1,
+        reads_byte: true,
+    };
+
+    /// A frame that writes the bytes. Bytes that don't fit into a frame are
+    /// cut off; the callers only build frames of up to one command and
+    /// [`DISPLAY_REGISTERS_COUNT`] data bytes.
+    pub(crate) fn new(bytes: &[u8]) -> Self {
+        let len = bytes.len().min(DISPLAY_REGISTERS_COUNT + 1);
+        let mut frame = Self {
+            bytes: [0; DISPLAY_REGISTERS_COUNT + 1],
+            len: len as u8,
+            reads_byte: false,
+        };
+        frame.bytes[..len].copy_from_slice(&bytes[..len]);
+        frame
+    }
+
+    /// A frame with the "address command" for register `pos`, followed by the
+    /// segments data that the TM1637 writes into the registers from `pos` on.
+    /// The caller checks that the data fits with [`registers_fit`].
+    pub(crate) fn segments(pos: u8, segments: &[u8]) -> Self {
+        let mut frame = Self::new(&[ISA::AddressCommandBase as u8 | pos]);
+        let len = segments.len().min(DISPLAY_REGISTERS_COUNT);
+        frame.bytes[1..=len].copy_from_slice(&segments[..len]);
+        frame.len += len as u8;
+        frame
+    }
+
+    /// A frame with the "display control"-command for the display state and
+    /// brightness bits.
+    pub(crate) fn display_control(brightness: u8) -> Self {
+        Self::new(&[ISA::DisplayCommandBase as u8 | brightness])
+    }
+
+    /// Returns the bytes that are written.
+    pub(crate) fn bytes(&self) -> &[u8] {
+        &self.bytes[..usize::from(self.len)]
+    }
+
+    /// Returns whether a byte is read after the bytes were written.
+    pub(crate) const fn reads_byte(&self) -> bool {
+        self.reads_byte
+    }
+}
+
+/// Returns whether `len` display registers starting at `pos` exist.
+pub(crate) const fn registers_fit(pos: u8, len: usize) -> bool {
+    pos as usize + len <= DISPLAY_REGISTERS_COUNT
+}
+
+/// Counts the retransmissions of a frame according to the [`AckPolicy`].
+#[derive(Debug)]
+pub(crate) struct Retransmits {
+    left: u8,
+}
+
+impl Retransmits {
+    pub(crate) const fn new(ack_policy: &AckPolicy) -> Self {
+        Self {
+            left: ack_policy.retransmits,
+        }
+    }
+
+    /// Returns whether a frame whose transmission ended with `res` is
+    /// transmitted again. Only frames that the TM1637 didn't acknowledge are
+    /// retransmitted, GPIO errors are not retried.
+    pub(crate) const fn retry<T, E>(&mut self, res: &Result<T, TM1637Error<E>>) -> bool {
+        match res {
+            Err(TM1637Error::NoAck(_)) if self.left > 0 => {
+                self.left -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl AckPolicy {
+    /// Returns how often DIO is sampled for an ACK (at least once).
+    pub(crate) fn samples(&self) -> u16 {
+        self.timeout.max(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame() {
+        assert_eq!(&[0xc2, 1, 2], Frame::segments(2, &[1, 2]).bytes());
+        assert_eq!(&[0x8f], Frame::display_control(0x0f).bytes());
+        assert!(!Frame::display_control(0x0f).reads_byte());
+        assert_eq!(&[0x42], Frame::READ_KEYS.bytes());
+        assert!(Frame::READ_KEYS.reads_byte());
+        assert!(registers_fit(2, 4));
+        assert!(!registers_fit(3, 4));
+    }
+
+    #[test]
+    fn test_retransmits() {
+        let mut retransmits = Retransmits::new(&AckPolicy {
+            timeout: 1,
+            retransmits: 1,
+        });
+        assert!(!retransmits.retry::<(), ()>(&Err(TM1637Error::Pin(()))));
+        assert!(retransmits.retry::<(), ()>(&Err(TM1637Error::NoAck(0x40))));
+        assert!(!retransmits.retry::<(), ()>(&Err(TM1637Error::NoAck(0x40))));
+    }
+}
@@ -0,0 +1,288 @@
+// MIT License
+//
+// Copyright (c) 2022 Philipp Schuster <phip1611@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Turns segment bytes back into something human-readable.
+//!
+//! This helps with logs and test failures: [`decode_char`] finds the char that a byte shows and
+//! [`SegmentArt`] draws a row of bytes as a 3-line 7-segment picture.
+//!
+//! ```
+//! use tm1637_gpio_driver::encoding::encode_string_with_points_into;
+//! use tm1637_gpio_driver::board::PointStyle;
+//! use tm1637_gpio_driver::render::SegmentArt;
+//!
+//! let mut bits = [0; 4];
+//! encode_string_with_points_into("12.34", PointStyle::DecimalPoint, &mut bits);
+//! let art = "     _   _      \n  |  _|  _| |_| \n  | |_ . _|   | ";
+//! assert_eq!(art, format!("{}", SegmentArt::new(&bits)));
+//! ```
+
+use crate::board::PointStyle;
+use crate::font::AsciiFont;
+use crate::mappings::{LoCharBits, NumCharBits, SegmentBits, SpecialCharBits, UpCharBits};
+use core::fmt::{Display, Formatter, Write};
+
+/// The chars of the mappings and their segments. If several chars have the
+/// same segments, the first one wins.
+#[rustfmt::skip]
+const MAPPINGS: [(u8, char); 42] = [
+    (NumCharBits::Zero as u8, '0'), (NumCharBits::One as u8, '1'),
+    (NumCharBits::Two as u8, '2'), (NumCharBits::Three as u8, '3'),
+    (NumCharBits::Four as u8, '4'), (NumCharBits::Five as u8, '5'),
+    (NumCharBits::Six as u8, '6'), (NumCharBits::Seven as u8, '7'),
+    (NumCharBits::Eight as u8, '8'), (NumCharBits::Nine as u8, '9'),
+    (UpCharBits::UpA as u8, 'A'), (UpCharBits::UpC as u8, 'C'),
+    (UpCharBits::UpE as u8, 'E'), (UpCharBits::UpF as u8, 'F'),
+    (UpCharBits::UpG as u8, 'G'), (UpCharBits::UpH as u8, 'H'),
+    (UpCharBits::UpI as u8, 'I'), (UpCharBits::UpJ as u8, 'J'),
+    (UpCharBits::UpL as u8, 'L'), (UpCharBits::UpO as u8, 'O'),
+    (UpCharBits::UpP as u8, 'P'), (UpCharBits::UpS as u8, 'S'),
+    (UpCharBits::UpU as u8, 'U'),
+    (LoCharBits::LoA as u8, 'a'), (LoCharBits::LoB as u8, 'b'),
+    (LoCharBits::LoC as u8, 'c'), (LoCharBits::LoD as u8, 'd'),
+    (LoCharBits::LoH as u8, 'h'), (LoCharBits::LoN as u8, 'n'),
+    (LoCharBits::LoO as u8, 'o'), (LoCharBits::LoQ as u8, 'q'),
+    (LoCharBits::LoR as u8, 'r'), (LoCharBits::LoT as u8, 't'),
+    (LoCharBits::LoU as u8, 'u'), (LoCharBits::LoY as u8, 'y'),
+    (SpecialCharBits::Space as u8, ' '), (SpecialCharBits::Minus as u8, '-'),
+    (SpecialCharBits::Underscore as u8, '_'), (SpecialCharBits::Equals as u8, '='),
+    (SpecialCharBits::QuestionMark as u8, '?'), (SpecialCharBits::Degree as u8, '°'),
+    (SpecialCharBits::Dot as u8, '.'),
+];
+
+/// Returns the char that the segments show best, or `None` if no char has
+/// these segments.
+///
+/// The chars of the [`crate::mappings`] are preferred, e.g. `0x3f` is `'0'`
+/// rather than `'O'`. Other bytes are looked up in [`AsciiFont::DEFAULT`].
+/// The point bit is ignored unless it is the only segment, which is `'.'`.
+pub const fn decode_char(segments: u8) -> Option<char> {
+    let segments = if segments == SegmentBits::SegPoint as u8 {
+        segments
+    } else {
+        segments & !(SegmentBits::SegPoint as u8)
+    };
+    let mut i = 0;
+    while i < MAPPINGS.len() {
+        if MAPPINGS[i].0 == segments {
+            return Some(MAPPINGS[i].1);
+        }
+        i += 1;
+    }
+    let mut c = ' ';
+    while c <= '~' {
+        match AsciiFont::DEFAULT.get(c) {
+            Some(glyph) if glyph == segments => return Some(c),
+            _ => {}
+        }
+        c = (c as u8 + 1) as char;
+    }
+    None
+}
+
+/// How [`SegmentArt`] draws the segments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ArtStyle {
+    /// With `_` and `|`.
+    #[default]
+    Ascii,
+    /// With Unicode box-drawing characters (`─`, `│`, `┌`, ...).
+    Unicode,
+}
+
+/// Draws a row of segment bytes as a 3-line 7-segment picture via
+/// [`Display`], without allocating memory.
+///
+/// With [`PointStyle::DecimalPoint`] each digit is followed by a column for
+/// its point; with [`PointStyle::Colon`] only the colon digit is followed by
+/// a column for the colon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SegmentArt<'a> {
+    segments: &'a [u8],
+    point_style: PointStyle,
+    style: ArtStyle,
+}
+
+impl<'a> SegmentArt<'a> {
+    /// Draws the segments in ASCII with decimal points.
+    pub const fn new(segments: &'a [u8]) -> Self {
+        Self {
+            segments,
+            point_style: PointStyle::DecimalPoint,
+            style: ArtStyle::Ascii,
+        }
+    }
+
+    /// Returns the picture with the points drawn as described by the point style.
+    pub const fn with_point_style(mut self, point_style: PointStyle) -> Self {
+        self.point_style = point_style;
+        self
+    }
+
+    /// Returns the picture drawn in the given style.
+    pub const fn with_style(mut self, style: ArtStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Writes the three columns of line `line` of a digit.
+    fn write_digit(&self, f: &mut Formatter<'_>, segments: u8, line: usize) -> core::fmt::Result {
+        let on = |segment: SegmentBits| segments & segment as u8 != 0;
+        let (a, b, c, d) = (
+            on(SegmentBits::SegA),
+            on(SegmentBits::SegB),
+            on(SegmentBits::SegC),
+            on(SegmentBits::SegD),
+        );
+        let (e, f_, g) = (
+            on(SegmentBits::SegE),
+            on(SegmentBits::SegF),
+            on(SegmentBits::SegG),
+        );
+        // each cell: (up, down, left, right) for the box-drawing characters
+        // and the ASCII char
+        let cells = match line {
+            0 => [
+                ((false, f_, false, a), ' '),
+                ((false, false, a, a), if a { '_' } else { ' ' }),
+                ((false, b, a, false), ' '),
+            ],
+            1 => [
+                ((f_, e, false, g), if f_ { '|' } else { ' ' }),
+                ((false, false, g, g), if g { '_' } else { ' ' }),
+                ((b, c, g, false), if b { '|' } else { ' ' }),
+            ],
+            _ => [
+                ((e, false, false, d), if e { '|' } else { ' ' }),
+                ((false, false, d, d), if d { '_' } else { ' ' }),
+                ((c, false, d, false), if c { '|' } else { ' ' }),
+            ],
+        };
+        for ((up, down, left, right), ascii) in cells {
+            f.write_char(match self.style {
+                ArtStyle::Ascii => ascii,
+                ArtStyle::Unicode => box_char(up, down, left, right),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Writes line `line` of the point column after digit `digit`, if any.
+    fn write_point(&self, f: &mut Formatter<'_>, digit: usize, line: usize) -> core::fmt::Result {
+        let point = self.segments[digit] & SegmentBits::SegPoint as u8 != 0;
+        match self.point_style {
+            PointStyle::DecimalPoint => f.write_char(if point && line == 2 { '.' } else { ' ' }),
+            PointStyle::Colon(colon) if usize::from(colon) == digit => {
+                f.write_char(if point && line == 1 { ':' } else { ' ' })
+            }
+            PointStyle::Colon(_) => Ok(()),
+        }
+    }
+}
+
+impl Display for SegmentArt<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        for line in 0..3 {
+            if line > 0 {
+                f.write_char('\n')?;
+            }
+            for (digit, &segments) in self.segments.iter().enumerate() {
+                self.write_digit(f, segments, line)?;
+                self.write_point(f, digit, line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the box-drawing character with lines in the given directions.
+const fn box_char(up: bool, down: bool, left: bool, right: bool) -> char {
+    match (up, down, left, right) {
+        (false, false, false, false) => ' ',
+        (true, false, false, false) => '╵',
+        (false, true, false, false) => '╷',
+        (false, false, true, false) => '╴',
+        (false, false, false, true) => '╶',
+        (true, true, false, false) => '│',
+        (false, false, true, true) => '─',
+        (false, true, false, true) => '┌',
+        (false, true, true, false) => '┐',
+        (true, false, false, true) => '└',
+        (true, false, true, false) => '┘',
+        (true, true, false, true) => '├',
+        (true, true, true, false) => '┤',
+        (false, true, true, true) => '┬',
+        (true, false, true, true) => '┴',
+        (true, true, true, true) => '┼',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::{encode_char, encode_digit};
+    use alloc::format;
+
+    #[test]
+    fn test_decode_char() {
+        for c in "0123456789ACEFGHIJLPU abdhnoqrtuy-_=?°".chars() {
+            assert_eq!(Some(c), decode_char(encode_char(c)), "{c}");
+        }
+        // mappings are preferred
+        assert_eq!(Some('0'), decode_char(encode_char('O')));
+        assert_eq!(Some('5'), decode_char(encode_char('S')));
+        // glyphs of the font
+        assert_eq!(Some('M'), decode_char(encode_char('M')));
+        assert_eq!(Some('c'), decode_char(LoCharBits::LoC as u8));
+        // the point
+        assert_eq!(
+            Some('7'),
+            decode_char(encode_digit(7) | SegmentBits::SegPoint as u8)
+        );
+        assert_eq!(Some('.'), decode_char(SegmentBits::SegPoint as u8));
+        assert_eq!(
+            None,
+            decode_char(SegmentBits::SegF as u8 | SegmentBits::SegC as u8)
+        );
+    }
+
+    #[test]
+    fn test_segment_art() {
+        let p = SegmentBits::SegPoint as u8;
+        let bits = [
+            encode_digit(1),
+            encode_digit(2) | p,
+            encode_digit(8),
+            encode_digit(0),
+        ];
+        let art = SegmentArt::new(&bits).with_point_style(PointStyle::Colon(1));
+        assert_eq!(
+            "    _   _  _ \n  | _|:|_|| |\n  ||_  |_||_|",
+            format!("{art}")
+        );
+        let art = art.with_style(ArtStyle::Unicode);
+        assert_eq!(
+            "  ╷╶─┐ ┌─┐┌─┐\n  │┌─┘:├─┤│ │\n  ╵└─╴ └─┘└─┘",
+            format!("{art}")
+        );
+    }
+}
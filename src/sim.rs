@@ -0,0 +1,732 @@
+// MIT License
+//
+// Copyright (c) 2022 Philipp Schuster <phip1611@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! A software simulation of the TM1637 for tests.
+//!
+//! [`Tm1637Simulator`] consumes the CLK/DIO transitions of the driver like the
+//! chip does: it detects start and stop conditions, samples the bits on the
+//! rising clock edges, executes the commands, writes the display registers
+//! and acknowledges every byte on DIO. Afterwards, you can check what the
+//! TM1637 would display:
+//!
+//! ```rust
+//! use tm1637_gpio_driver::sim::Tm1637Simulator;
+//! use tm1637_gpio_driver::{DisplayState, TM1637};
+//!
+//! let sim = Tm1637Simulator::new();
+//! let display = TM1637::from_pins(&sim).unwrap();
+//! display.try_write_segments_raw(&[0x3f, 0x06], 0).unwrap();
+//! assert_eq!([0x3f, 0x06, 0, 0, 0, 0], sim.registers());
+//! assert_eq!(DisplayState::ON, sim.display_state());
+//! ```
+//!
+//! The simulation time advances with each bit delay of the driver. Enable
+//! [`Tm1637Simulator::set_timing_limits`] to check the timing of the bus
+//! signals with a [`TimingValidator`].
+//!
+//! This feature must be activated in your Cargo.toml if you want to use it.
+
+#[cfg(feature = "alloc")]
+use crate::TM1637Adapter;
+use crate::keys::KeyCode;
+use crate::timing::{PinEvent, PinEventKind, TimingLimits, TimingValidator, TimingViolation};
+use crate::{Brightness, DISPLAY_REGISTERS_COUNT, DisplayState, GpioPinValue, Tm1637Pins};
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, rc::Rc};
+use core::cell::Cell;
+use core::convert::Infallible;
+
+/// Where the simulated TM1637 is within the transmission of a byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    /// No frame was started; the bus is ignored until a start condition.
+    Idle,
+    /// Receiving the bits of a byte.
+    Receive,
+    /// All 8 bits are received; DIO is pulled low at the next falling clock edge.
+    AckPending,
+    /// DIO is pulled low; waiting for the 9th rising clock edge.
+    Ack,
+    /// The 9th clock is high; DIO is released at the next falling clock edge.
+    AckClock,
+    /// Sending bit `n` of the key-scan data.
+    Transmit(u8),
+    /// All bits of the key-scan data are sent; waiting for the stop condition.
+    Done,
+}
+
+/// The internal state of the simulated TM1637.
+#[derive(Debug, Clone, Copy)]
+struct State {
+    /// Level of CLK.
+    clk: bool,
+    /// Level that the driver puts on DIO. HIGH releases the line.
+    dio: bool,
+    /// Whether the driver drives DIO high actively instead of releasing it.
+    dio_driven_high: bool,
+    /// Whether the TM1637 pulls DIO low.
+    pull_low: bool,
+    phase: Phase,
+    /// Bits of the byte that is currently received, LSB first.
+    byte: u8,
+    /// Number of received bits of the current byte.
+    bits: u8,
+    /// Number of received bytes in the current frame.
+    bytes: u16,
+    /// Whether the last data command requested the key-scan data.
+    read_keys: bool,
+    /// Whether the last data command selected the fixed address mode.
+    fixed_address: bool,
+    /// The display register that is written next, if an address was set.
+    address: Option<u8>,
+    registers: [u8; DISPLAY_REGISTERS_COUNT],
+    /// Bits 3-0 of the last "display control"-command.
+    display_control: u8,
+    scan_code: u8,
+    bit_delay_ns: u32,
+    elapsed_ns: u64,
+    frames: u32,
+    /// Number of start conditions within a frame.
+    restarts: u32,
+    /// Number of times that the driver drove DIO high while the TM1637 pulled it low.
+    contentions: u32,
+    /// Checks the timing of the signals, if enabled.
+    timing: Option<TimingValidator>,
+    timing_violations: u32,
+    first_timing_violation: Option<TimingViolation>,
+}
+
+/// Simulated TM1637 that is connected to the driver via [`Tm1637Pins`].
+///
+/// The pins are implemented for `&Tm1637Simulator` so that you can
+/// inspect the simulator while the driver uses it. The simulation time only
+/// advances in [`Tm1637Pins::bit_delay`], so tests don't need to wait.
+#[derive(Debug)]
+pub struct Tm1637Simulator {
+    state: Cell<State>,
+}
+
+// `Cell::get` is `const` since Rust 1.88, which is above our MSRV
+#[allow(clippy::missing_const_for_fn)]
+impl Tm1637Simulator {
+    /// Creates a simulated TM1637 that was just powered on: all display
+    /// registers are 0, the display is off and no key is pressed.
+    pub const fn new() -> Self {
+        Self {
+            state: Cell::new(State {
+                clk: true,
+                dio: true,
+                dio_driven_high: false,
+                pull_low: false,
+                phase: Phase::Idle,
+                byte: 0,
+                bits: 0,
+                bytes: 0,
+                read_keys: false,
+                fixed_address: false,
+                address: None,
+                registers: [0; DISPLAY_REGISTERS_COUNT],
+                display_control: 0,
+                scan_code: KeyCode::NO_KEY_SCAN_CODE,
+                bit_delay_ns: 0,
+                elapsed_ns: 0,
+                frames: 0,
+                restarts: 0,
+                contentions: 0,
+                timing: None,
+                timing_violations: 0,
+                first_timing_violation: None,
+            }),
+        }
+    }
+
+    /// Returns the content of the display registers.
+    pub fn registers(&self) -> [u8; DISPLAY_REGISTERS_COUNT] {
+        self.state.get().registers
+    }
+
+    /// Returns whether the display is on or off.
+    pub fn display_state(&self) -> DisplayState {
+        DisplayState::try_from(self.state.get().display_control & DisplayState::ON as u8)
+            .unwrap_or_else(|_| unreachable!("only the display state bit is used"))
+    }
+
+    /// Returns the brightness of the display.
+    pub fn brightness(&self) -> Brightness {
+        Brightness::try_from(self.state.get().display_control & 0b0000_0111)
+            .unwrap_or_else(|_| unreachable!("brightness has 3 bits"))
+    }
+
+    /// Sets the key that is reported by the next key scan.
+    pub fn set_pressed_key(&self, key: KeyCode) {
+        self.update(|s| s.scan_code = key.scan_code());
+    }
+
+    /// Sets by how much the simulation time advances with each bit delay.
+    pub fn set_bit_delay_ns(&self, bit_delay_ns: u32) {
+        self.update(|s| s.bit_delay_ns = bit_delay_ns);
+    }
+
+    /// Returns the simulation time, i.e. the sum of all bit delays.
+    pub fn elapsed_ns(&self) -> u64 {
+        self.state.get().elapsed_ns
+    }
+
+    /// Enables the check of the bus timing against the limits, based on the
+    /// simulation time. Resets the violations that were found so far.
+    pub fn set_timing_limits(&self, limits: TimingLimits) {
+        self.update(|s| {
+            s.timing = Some(TimingValidator::new(limits));
+            s.timing_violations = 0;
+            s.first_timing_violation = None;
+        });
+    }
+
+    /// Returns the number of timing violations since the check was enabled.
+    pub fn timing_violations(&self) -> u32 {
+        self.state.get().timing_violations
+    }
+
+    /// Returns the first timing violation since the check was enabled.
+    pub fn first_timing_violation(&self) -> Option<TimingViolation> {
+        self.state.get().first_timing_violation
+    }
+
+    /// Returns the number of frames, i.e. of completed transmissions between
+    /// a start and a stop condition.
+    pub fn frames(&self) -> u32 {
+        self.state.get().frames
+    }
+
+    /// Returns the number of start conditions within a frame, i.e. before
+    /// the previous frame was stopped. The TM1637 aborts the frame then. Such
+    /// a start condition is caused, for example, by DIO falling while CLK is
+    /// high in the middle of a byte.
+    pub fn restarts(&self) -> u32 {
+        self.state.get().restarts
+    }
+
+    /// Returns how often the driver waited or read DIO while it drove DIO high
+    /// and the TM1637 pulled it low, e.g. because it wrote HIGH instead of
+    /// releasing DIO for the ACK. With a push-pull pin, both would work
+    /// against each other.
+    pub fn contentions(&self) -> u32 {
+        self.state.get().contentions
+    }
+
+    /// The driver writes CLK.
+    fn write_clk(&self, value: GpioPinValue) {
+        self.update(|s| {
+            s.check_timing(PinEventKind::WriteClk(value));
+            s.clk_changed(matches!(value, GpioPinValue::HIGH));
+        });
+    }
+
+    /// The driver writes DIO.
+    fn write_dio(&self, value: GpioPinValue) {
+        self.update(|s| {
+            s.check_timing(PinEventKind::WriteDio(value));
+            s.dio_driven_high = matches!(value, GpioPinValue::HIGH);
+            s.dio_changed(s.dio_driven_high);
+        });
+    }
+
+    /// The driver releases DIO.
+    fn release_dio(&self) {
+        self.update(|s| {
+            s.check_timing(PinEventKind::WriteDio(GpioPinValue::HIGH));
+            s.dio_driven_high = false;
+            s.dio_changed(true);
+        });
+    }
+
+    /// The driver reads DIO.
+    fn read_dio(&self) -> GpioPinValue {
+        self.update(State::check_contention);
+        // open drain: the line is low if one of both pulls it low
+        let state = self.state.get();
+        GpioPinValue::from(u8::from(state.dio && !state.pull_low))
+    }
+
+    /// The driver waits.
+    fn bit_delay(&self) {
+        self.update(|s| {
+            s.check_contention();
+            s.elapsed_ns += u64::from(s.bit_delay_ns);
+        });
+    }
+
+    /// The driver waits for the given time.
+    fn delay_ns(&self, ns: u32) {
+        self.update(|s| {
+            s.check_contention();
+            s.elapsed_ns += u64::from(ns);
+        });
+    }
+
+    /// Applies the change to the state.
+    fn update(&self, f: impl FnOnce(&mut State)) {
+        let mut state = self.state.get();
+        f(&mut state);
+        self.state.set(state);
+    }
+}
+
+/// Setups an adapter that is connected to a simulated TM1637.
+/// Use the returned simulator to check what the TM1637 would display.
+#[cfg(feature = "alloc")]
+pub fn setup_simulator() -> (TM1637Adapter, Rc<Tm1637Simulator>) {
+    let sim = Rc::new(Tm1637Simulator::new());
+    let (clk, dio, read, delay) = (sim.clone(), sim.clone(), sim.clone(), sim.clone());
+    let adapter = TM1637Adapter::new(
+        Box::from(move |bit| clk.write_clk(bit)),
+        Box::from(move |bit| dio.write_dio(bit)),
+        // like the GPIO backends, reading switches DIO to input
+        Box::from(move || {
+            read.release_dio();
+            read.read_dio()
+        }),
+        Box::from(move || delay.bit_delay()),
+    );
+    (adapter, sim)
+}
+
+impl Default for Tm1637Simulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl State {
+    /// Passes the event to the timing validator, if enabled.
+    fn check_timing(&mut self, kind: PinEventKind) {
+        let event = PinEvent {
+            time_ns: self.elapsed_ns,
+            kind,
+        };
+        if let Some(timing) = self.timing.as_mut() {
+            for violation in timing.check(event) {
+                self.timing_violations += 1;
+                self.first_timing_violation.get_or_insert(violation);
+            }
+        }
+    }
+
+    /// Counts a contention if the driver drives DIO high while the TM1637
+    /// pulls it low. This is checked when the driver waits or reads, i.e. not
+    /// for the moment between the falling clock edge and releasing DIO.
+    const fn check_contention(&mut self) {
+        if self.dio_driven_high && self.pull_low {
+            self.contentions += 1;
+        }
+    }
+
+    /// The driver changed CLK.
+    fn clk_changed(&mut self, clk: bool) {
+        let rising = !self.clk && clk;
+        let falling = self.clk && !clk;
+        self.clk = clk;
+
+        match self.phase {
+            // data is sampled while CLK is high
+            Phase::Receive if rising => {
+                self.byte |= u8::from(self.dio) << self.bits;
+                self.bits += 1;
+                if self.bits == 8 {
+                    self.execute(self.byte);
+                    self.phase = Phase::AckPending;
+                }
+            }
+            // ACK from the falling edge of the 8th clock ...
+            Phase::AckPending if falling => {
+                self.pull_low = true;
+                self.phase = Phase::Ack;
+            }
+            Phase::Ack if rising => self.phase = Phase::AckClock,
+            // ... until the falling edge of the 9th clock
+            Phase::AckClock if falling => {
+                self.pull_low = false;
+                if self.read_keys && self.bytes == 1 {
+                    // the key-scan data follows the command
+                    self.transmit(0);
+                } else {
+                    self.phase = Phase::Receive;
+                    self.byte = 0;
+                    self.bits = 0;
+                }
+            }
+            // the next bit is put on DIO while CLK is low
+            Phase::Transmit(bit) if falling => self.transmit(bit + 1),
+            _ => {}
+        }
+    }
+
+    /// The driver changed DIO. Start and stop conditions are detected on
+    /// the line, i.e. not while the TM1637 pulls it low.
+    fn dio_changed(&mut self, dio: bool) {
+        let line = self.dio && !self.pull_low;
+        let new_line = dio && !self.pull_low;
+        let falling = line && !new_line;
+        let rising = !line && new_line;
+        self.dio = dio;
+
+        if self.clk && falling {
+            // start condition
+            if self.phase != Phase::Idle {
+                self.restarts += 1;
+            }
+            self.phase = Phase::Receive;
+            self.pull_low = false;
+            self.byte = 0;
+            self.bits = 0;
+            self.bytes = 0;
+        } else if self.clk && rising && self.phase != Phase::Idle {
+            // stop condition
+            self.phase = Phase::Idle;
+            self.pull_low = false;
+            self.frames += 1;
+        }
+    }
+
+    /// Puts bit `bit` of the key-scan data on DIO.
+    const fn transmit(&mut self, bit: u8) {
+        if bit < 8 {
+            self.pull_low = self.scan_code & (1 << bit) == 0;
+            self.phase = Phase::Transmit(bit);
+        } else {
+            self.pull_low = false;
+            self.phase = Phase::Done;
+        }
+    }
+
+    /// Executes a received byte. The first byte of a frame is a command,
+    /// all further bytes are display data.
+    fn execute(&mut self, byte: u8) {
+        self.bytes += 1;
+        if self.bytes > 1 {
+            if let Some(address) = self
+                .address
+                .filter(|&a| (a as usize) < DISPLAY_REGISTERS_COUNT)
+            {
+                self.registers[address as usize] = byte;
+                if !self.fixed_address {
+                    self.address = Some(address + 1);
+                }
+            }
+            return;
+        }
+
+        match byte & 0b1100_0000 {
+            0b0100_0000 => {
+                self.read_keys = byte & 0b10 != 0;
+                self.fixed_address = byte & 0b100 != 0;
+            }
+            0b1000_0000 => self.display_control = byte & 0b0000_1111,
+            0b1100_0000 => self.address = Some(byte & 0b0000_0111),
+            // not a command
+            _ => {}
+        }
+    }
+}
+
+impl Tm1637Pins for &Tm1637Simulator {
+    type Error = Infallible;
+
+    fn write_clk(&mut self, value: GpioPinValue) -> Result<(), Self::Error> {
+        Tm1637Simulator::write_clk(self, value);
+        Ok(())
+    }
+
+    fn write_dio(&mut self, value: GpioPinValue) -> Result<(), Self::Error> {
+        Tm1637Simulator::write_dio(self, value);
+        Ok(())
+    }
+
+    fn release_dio(&mut self) -> Result<(), Self::Error> {
+        Tm1637Simulator::release_dio(self);
+        Ok(())
+    }
+
+    fn read_dio(&mut self) -> Result<GpioPinValue, Self::Error> {
+        Ok(Tm1637Simulator::read_dio(self))
+    }
+
+    fn bit_delay(&mut self) {
+        Tm1637Simulator::bit_delay(self);
+    }
+
+    fn delay_ns(&mut self, ns: u32) {
+        Tm1637Simulator::delay_ns(self, ns);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{BoardProfile, Orientation};
+    use crate::encoding::encode_digit;
+    use crate::keys::KeySegment;
+    use crate::mappings::SegmentBits;
+    use crate::{AckPolicy, BusRecovery, ISA, TM1637, TM1637Error};
+
+    #[test]
+    fn test_display() {
+        let sim = Tm1637Simulator::new();
+        let mut display = TM1637::from_pins(&sim).unwrap();
+        assert_eq!(DisplayState::OFF, sim.display_state());
+
+        display
+            .try_write_segments_raw(&[1, 2, 3, 4, 5, 6], 0)
+            .unwrap();
+        assert_eq!([1, 2, 3, 4, 5, 6], sim.registers());
+        assert_eq!(DisplayState::ON, sim.display_state());
+        assert_eq!(Brightness::L7, sim.brightness());
+        // data command, address command + data, display control
+        assert_eq!(3, sim.frames());
+
+        display
+            .try_write_segments_fixed(&[(5, 0x3f), (0, 0x06)])
+            .unwrap();
+        assert_eq!([0x06, 2, 3, 4, 5, 0x3f], sim.registers());
+
+        display.set_brightness(Brightness::L2);
+        display.set_display_state(DisplayState::OFF);
+        display.try_write_display_state().unwrap();
+        assert_eq!(DisplayState::OFF, sim.display_state());
+        assert_eq!(Brightness::L2, sim.brightness());
+
+        // every byte is acknowledged with the first sample
+        display.set_ack_policy(AckPolicy {
+            timeout: 1,
+            retransmits: 0,
+        });
+        display.try_clear().unwrap();
+        assert_eq!([0; DISPLAY_REGISTERS_COUNT], sim.registers());
+    }
+
+    #[test]
+    fn test_write_long_slice() {
+        let sim = Tm1637Simulator::new();
+        let display = TM1637::from_pins(&sim).unwrap();
+        // 300 bytes would wrap around to 44 if the length was narrowed to u8
+        let segments = [0x3f; 300];
+        assert_eq!(
+            Err(TM1637Error::InvalidPosition),
+            display.try_write_segments_raw(&segments, 0)
+        );
+        assert_eq!(0, sim.frames());
+
+        display.write_segments_raw(&segments, 2);
+        assert_eq!([0, 0, 0x3f, 0x3f, 0x3f, 0x3f], sim.registers());
+    }
+
+    #[test]
+    fn test_read_keys() {
+        let sim = Tm1637Simulator::new();
+        let display = TM1637::from_pins(&sim).unwrap();
+        assert_eq!(Ok(KeyCode::NoKey), display.try_read_keys());
+
+        sim.set_pressed_key(KeyCode::K2(KeySegment::SG3));
+        assert_eq!(Ok(KeyCode::K2(KeySegment::SG3)), display.try_read_keys());
+
+        // writing still works afterwards
+        display.try_write_segments_raw(&[0x3f], 2).unwrap();
+        assert_eq!([0, 0, 0x3f, 0, 0, 0], sim.registers());
+    }
+
+    #[test]
+    fn test_release_dio() {
+        let sim = Tm1637Simulator::new();
+        let display = TM1637::from_pins(&sim).unwrap();
+        display.try_write_segments_raw(&[1, 2, 3], 0).unwrap();
+        sim.set_pressed_key(KeyCode::K2(KeySegment::SG1));
+        assert_eq!(Ok(KeyCode::K2(KeySegment::SG1)), display.try_read_keys());
+        display.try_recover_bus().unwrap();
+        // DIO is released for every ACK and the key-scan data
+        assert_eq!(0, sim.contentions());
+        assert_eq!(0, sim.restarts());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_read_keys_without_restart() {
+        let (adapter, sim) = setup_simulator();
+        // "no key" is all 1-bits, DIO must not fall while CLK is high for them
+        assert_eq!(KeyCode::NoKey, adapter.read_keys());
+        sim.set_pressed_key(KeyCode::K1(KeySegment::SG5));
+        assert_eq!(KeyCode::K1(KeySegment::SG5), adapter.read_keys());
+        assert_eq!(0, sim.restarts());
+        assert_eq!(2, sim.frames());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_setup_simulator() {
+        let (display, sim) = setup_simulator();
+        display.write_segments_raw(&[0x3f, 0x06], 4);
+        assert_eq!([0, 0, 0, 0, 0x3f, 0x06], sim.registers());
+    }
+
+    #[test]
+    fn test_elapsed_time() {
+        let sim = Tm1637Simulator::new();
+        sim.set_bit_delay_ns(1000);
+        let display = TM1637::from_pins(&sim).unwrap();
+        display.try_write_display_state().unwrap();
+        // start (2), 8 bits (2 each), ACK (3), stop (2)
+        assert_eq!(23_000, sim.elapsed_ns());
+    }
+
+    #[test]
+    fn test_timing() {
+        use crate::timing::TimingRule;
+
+        let sim = Tm1637Simulator::new();
+        sim.set_bit_delay_ns(400);
+        sim.set_timing_limits(TimingLimits::DATASHEET);
+        let display = TM1637::from_pins(&sim).unwrap();
+        display.try_write_segments_raw(&[1, 2, 3, 4], 0).unwrap();
+        display.try_read_keys().unwrap();
+        assert_eq!(None, sim.first_timing_violation());
+
+        sim.set_bit_delay_ns(300);
+        sim.set_timing_limits(TimingLimits::DATASHEET);
+        display.try_write_display_state().unwrap();
+        // CLK is high and low for 300ns: 9 clock cycles and the rising edge of
+        // the stop condition
+        assert_eq!(19, sim.timing_violations());
+        let violation = sim.first_timing_violation().unwrap();
+        assert_eq!(TimingRule::ClkLowWidth, violation.rule);
+        assert_eq!(0, violation.position.bit);
+    }
+
+    #[test]
+    fn test_timing_profile() {
+        use crate::timing::TimingProfile;
+
+        let sim = Tm1637Simulator::new();
+        // would violate every limit if the profile weren't used
+        sim.set_bit_delay_ns(0);
+        let mut display = TM1637::from_pins(&sim).unwrap();
+        display.set_timing_profile(Some(TimingProfile::DATASHEET_MIN));
+        display.try_write_segments_raw(&[1, 2, 3, 4], 0).unwrap();
+        display.try_read_keys().unwrap();
+        assert_eq!(None, sim.first_timing_violation());
+        assert_eq!([1, 2, 3, 4, 0, 0], sim.registers());
+    }
+
+    /// Leaves the simulator in the state of a process that was killed while
+    /// the TM1637 sent the key-scan data and pulls DIO low.
+    fn abort_read_keys(sim: &Tm1637Simulator) {
+        sim.set_pressed_key(KeyCode::K1(KeySegment::SG8));
+        let clock = |bit: u8| {
+            sim.write_clk(GpioPinValue::LOW);
+            sim.write_dio(GpioPinValue::from(bit));
+            sim.write_clk(GpioPinValue::HIGH);
+        };
+        // start condition
+        clock(1);
+        sim.write_dio(GpioPinValue::LOW);
+        // "read key scan data"-command and its ACK
+        for i in 0..8 {
+            clock((ISA::DataCommandReadKeys as u8 >> i) & 1);
+        }
+        clock(1);
+        // 2 bits of the scan code 0xf0
+        clock(1);
+        clock(1);
+        sim.write_clk(GpioPinValue::LOW);
+        assert_eq!(GpioPinValue::LOW, sim.read_dio());
+    }
+
+    #[test]
+    fn test_recover_bus() {
+        let sim = Tm1637Simulator::new();
+        abort_read_keys(&sim);
+        let display = TM1637::from_pins(&sim).unwrap();
+        // the start condition gets lost
+        assert!(display.try_write_segments_raw(&[1, 2, 3, 4], 0).is_err());
+        assert_eq!([0; DISPLAY_REGISTERS_COUNT], sim.registers());
+
+        let sim = Tm1637Simulator::new();
+        abort_read_keys(&sim);
+        let bus_recovery = BusRecovery {
+            on_init: true,
+            after_nack: false,
+        };
+        let display = TM1637::from_pins_with_bus_recovery(&sim, bus_recovery).unwrap();
+        // the bus is in sync before anything is written
+        assert!(sim.frames() > 0);
+        display.try_write_segments_raw(&[1, 2, 3, 4], 0).unwrap();
+        assert_eq!([1, 2, 3, 4, 0, 0], sim.registers());
+
+        // also works on demand
+        abort_read_keys(&sim);
+        display.try_recover_bus().unwrap();
+        display.try_write_segments_raw(&[5, 6], 4).unwrap();
+        assert_eq!([1, 2, 3, 4, 5, 6], sim.registers());
+    }
+
+    #[test]
+    fn test_orientation() {
+        let point = SegmentBits::SegPoint as u8;
+        let rotated = |digit| Orientation::UPSIDE_DOWN.transform_segments(encode_digit(digit));
+
+        // the colon stays on its grid
+        let sim = Tm1637Simulator::new();
+        let mut display = TM1637::from_pins(&sim).unwrap();
+        display.set_board_profile(BoardProfile::FOUR_DIGIT);
+        display.set_orientation(Orientation::UPSIDE_DOWN);
+        let digits = [
+            encode_digit(1),
+            encode_digit(2) | point,
+            encode_digit(3),
+            encode_digit(4),
+        ];
+        display.try_write_segments_raw(&digits, 0).unwrap();
+        assert_eq!(
+            [rotated(4), rotated(3) | point, rotated(2), rotated(1), 0, 0],
+            sim.registers()
+        );
+        assert_eq!(digits, display.segments()[..4]);
+
+        // decimal points move to the following digit
+        let sim = Tm1637Simulator::new();
+        let mut display = TM1637::from_pins(&sim).unwrap();
+        display.set_orientation(Orientation::UPSIDE_DOWN);
+        display
+            .try_write_segments_raw(&[encode_digit(1) | point, encode_digit(2)], 0)
+            .unwrap();
+        assert_eq!(
+            [0, 0, 0, 0, rotated(2) | point, rotated(1)],
+            sim.registers()
+        );
+
+        display
+            .try_write_segment_raw(encode_digit(3) | point, 2)
+            .unwrap();
+        assert_eq!(
+            [0, 0, point, rotated(3), rotated(2) | point, rotated(1)],
+            sim.registers()
+        );
+    }
+}
@@ -0,0 +1,482 @@
+// MIT License
+//
+// Copyright (c) 2022 Philipp Schuster <phip1611@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Checks the timing of the bus signals against the minima of the TM1637
+//! data sheet.
+//!
+//! Too short bit delays are a common reason for flaky displays: the TM1637
+//! samples DIO at the rising edge of CLK and needs the signals to be stable
+//! for a while around it. [`TimingValidator`] consumes timestamped
+//! [`PinEvent`]s, e.g. from a log of your GPIO backend or from the simulator
+//! (feature `sim`), and reports each [`TimingViolation`] together with its
+//! [`BusPosition`].
+
+use crate::GpioPinValue;
+use core::fmt::{Display, Formatter};
+
+/// Minimum durations of the bus signals in nanoseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimingLimits {
+    /// Minimum time that CLK must stay high or low.
+    pub clk_pulse_width_ns: u32,
+    /// Minimum time that DIO must be stable before the rising edge of CLK.
+    pub data_setup_ns: u32,
+    /// Minimum time that DIO must be stable after the rising edge of CLK.
+    /// Also the minimum time that CLK must stay high after a start condition.
+    pub data_hold_ns: u32,
+}
+
+impl TimingLimits {
+    /// The minima from the data sheet of the TM1637.
+    pub const DATASHEET: Self = Self {
+        clk_pulse_width_ns: 400,
+        data_setup_ns: 100,
+        data_hold_ns: 100,
+    };
+}
+
+impl Default for TimingLimits {
+    fn default() -> Self {
+        Self::DATASHEET
+    }
+}
+
+/// Durations of the phases of a bus transfer in nanoseconds. The driver
+/// waits for each phase with [`crate::Tm1637Pins::delay_ns`] instead of
+/// calling [`crate::Tm1637Pins::bit_delay`] for all of them.
+///
+/// Slow GPIO backends take microseconds for a single pin write anyway, so
+/// the presets for them leave extra margin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimingProfile {
+    /// Time CLK is high before DIO changes for a start or stop condition.
+    pub setup_ns: u32,
+    /// Time CLK stays high after DIO changed for a start or stop condition.
+    pub hold_ns: u32,
+    /// Time CLK is high while a bit is clocked.
+    pub clk_high_ns: u32,
+    /// Time CLK is low while a bit is put on DIO. This includes the setup
+    /// time of the bit.
+    pub clk_low_ns: u32,
+    /// Time between two samples of DIO while waiting for the acknowledge.
+    pub ack_wait_ns: u32,
+}
+
+impl TimingProfile {
+    /// The minima from the data sheet of the TM1637, see
+    /// [`TimingLimits::DATASHEET`]. Only use this with a precise delay.
+    pub const DATASHEET_MIN: Self = Self {
+        setup_ns: 100,
+        hold_ns: 100,
+        clk_high_ns: 400,
+        clk_low_ns: 400,
+        ack_wait_ns: 400,
+    };
+
+    /// For a Raspberry Pi with the GPIO character device (`gpio_cdev`).
+    pub const RASPBERRY_PI_CDEV: Self = Self {
+        setup_ns: 1_000,
+        hold_ns: 1_000,
+        clk_high_ns: 5_000,
+        clk_low_ns: 5_000,
+        ack_wait_ns: 5_000,
+    };
+
+    /// For slow backends like the sysfs GPIO interface.
+    pub const SLOW_SYSFS: Self = Self {
+        setup_ns: 10_000,
+        hold_ns: 10_000,
+        clk_high_ns: 50_000,
+        clk_low_ns: 50_000,
+        ack_wait_ns: 100_000,
+    };
+}
+
+impl Default for TimingProfile {
+    fn default() -> Self {
+        Self::DATASHEET_MIN
+    }
+}
+
+/// Busy-waiting delay provider for `std` targets.
+///
+/// `std::thread::sleep` is far too coarse for the TM1637, so this spins
+/// instead. The number of loop iterations per microsecond is calibrated once
+/// in [`BusyWait::calibrate`].
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BusyWait {
+    /// Loop iterations per microsecond.
+    iterations_per_us: u64,
+}
+
+#[cfg(feature = "std")]
+impl BusyWait {
+    /// Measures how fast the busy loop runs on this machine.
+    pub fn calibrate() -> Self {
+        const ITERATIONS: u64 = 1_000_000;
+        let start = std::time::Instant::now();
+        Self::spin(ITERATIONS);
+        let elapsed_us = start.elapsed().as_micros().max(1);
+        let iterations_per_us = (u128::from(ITERATIONS) / elapsed_us).max(1);
+        Self {
+            iterations_per_us: u64::try_from(iterations_per_us).unwrap_or(u64::MAX),
+        }
+    }
+
+    /// Waits for at least the given time. Delays below one microsecond are
+    /// rounded up to one microsecond.
+    pub fn delay_ns(&self, ns: u32) {
+        let us = u64::from(ns.div_ceil(1000).max(1));
+        Self::spin(us.saturating_mul(self.iterations_per_us));
+    }
+
+    /// Loops the given number of times without being optimized away.
+    fn spin(iterations: u64) {
+        for i in 0..iterations {
+            core::hint::black_box(i);
+            core::hint::spin_loop();
+        }
+    }
+}
+
+/// What happened on the bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PinEventKind {
+    /// The driver wrote the value to CLK.
+    WriteClk(GpioPinValue),
+    /// The driver wrote the value to DIO.
+    WriteDio(GpioPinValue),
+    /// The driver read the value from DIO.
+    ReadDio(GpioPinValue),
+    /// The driver waited after a bit had been written to a pin.
+    BitDelay {
+        /// How long the driver waited.
+        duration_ns: u64,
+    },
+}
+
+/// Something that happened on the bus at a point in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PinEvent {
+    /// Monotonic timestamp in nanoseconds.
+    pub time_ns: u64,
+    /// What happened.
+    pub kind: PinEventKind,
+}
+
+/// The timing rule of the data sheet that was violated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimingRule {
+    /// CLK was high for less than [`TimingLimits::clk_pulse_width_ns`].
+    ClkHighWidth,
+    /// CLK was low for less than [`TimingLimits::clk_pulse_width_ns`].
+    ClkLowWidth,
+    /// DIO changed less than [`TimingLimits::data_setup_ns`] before the rising
+    /// edge of CLK.
+    DataSetup,
+    /// DIO changed less than [`TimingLimits::data_hold_ns`] after the rising
+    /// edge of CLK.
+    DataHold,
+    /// CLK went low less than [`TimingLimits::data_hold_ns`] after a start
+    /// condition.
+    StartHold,
+}
+
+/// Position within the transmission where a violation happened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BusPosition {
+    /// Index of the frame, i.e. of the start condition, beginning with 0.
+    pub frame: u32,
+    /// Index of the byte within the frame.
+    pub byte: u16,
+    /// Clock cycle within the byte: 0 to 7 are the data bits (LSB first),
+    /// 8 is the ACK. The rising edge of the stop condition counts as bit 0
+    /// of the next byte.
+    pub bit: u8,
+}
+
+/// A violated timing rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimingViolation {
+    /// The violated rule.
+    pub rule: TimingRule,
+    /// Where the violation happened.
+    pub position: BusPosition,
+    /// Timestamp of the event that completed the violation.
+    pub time_ns: u64,
+    /// The measured duration.
+    pub actual_ns: u64,
+    /// The minimum duration.
+    pub min_ns: u32,
+}
+
+impl Display for TimingViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{:?} violated at {}ns (frame {}, byte {}, bit {}): {}ns < {}ns",
+            self.rule,
+            self.time_ns,
+            self.position.frame,
+            self.position.byte,
+            self.position.bit,
+            self.actual_ns,
+            self.min_ns
+        )
+    }
+}
+
+/// Checks a stream of [`PinEvent`]s against [`TimingLimits`].
+///
+/// Only changes of the levels count; writing the current level again is
+/// ignored. The first write to a pin only establishes its level.
+#[derive(Debug, Clone, Copy)]
+pub struct TimingValidator {
+    limits: TimingLimits,
+    /// Level of CLK and time of its last change.
+    clk: Option<(bool, u64)>,
+    /// Level of DIO and time of its last change.
+    dio: Option<(bool, u64)>,
+    /// Time of the last rising edge of CLK.
+    clk_rise: Option<u64>,
+    /// Time of the start condition, while CLK is still high afterwards.
+    start: Option<u64>,
+    /// Whether a frame was started and not stopped yet.
+    in_frame: bool,
+    /// Number of start conditions.
+    frames: u32,
+    /// Number of rising edges of CLK in the current frame.
+    clocks: u32,
+}
+
+impl TimingValidator {
+    /// Creates a validator that didn't see any events yet.
+    pub const fn new(limits: TimingLimits) -> Self {
+        Self {
+            limits,
+            clk: None,
+            dio: None,
+            clk_rise: None,
+            start: None,
+            in_frame: false,
+            frames: 0,
+            clocks: 0,
+        }
+    }
+
+    /// Checks all events and returns the violations.
+    pub fn validate<I: IntoIterator<Item = PinEvent>>(
+        mut self,
+        events: I,
+    ) -> impl Iterator<Item = TimingViolation> {
+        events.into_iter().flat_map(move |event| self.check(event))
+    }
+
+    /// Checks the next event. Events must be passed in chronological order.
+    /// Returns the violations that this event completes; there can be up to
+    /// two at a rising edge of CLK.
+    pub fn check(&mut self, event: PinEvent) -> impl Iterator<Item = TimingViolation> + use<> {
+        let now = event.time_ns;
+        let mut violations = [None, None];
+        match event.kind {
+            PinEventKind::WriteClk(value) => {
+                let high = value == GpioPinValue::HIGH;
+                match self.clk {
+                    Some((level, _)) if level == high => {}
+                    Some((_, since)) => {
+                        if high {
+                            self.clk = Some((high, now));
+                            self.clk_rise = Some(now);
+                            self.clocks += 1;
+                            violations[0] = self.violation(
+                                TimingRule::ClkLowWidth,
+                                now,
+                                since,
+                                self.limits.clk_pulse_width_ns,
+                            );
+                            violations[1] = self.dio.and_then(|(_, changed)| {
+                                self.violation(
+                                    TimingRule::DataSetup,
+                                    now,
+                                    changed,
+                                    self.limits.data_setup_ns,
+                                )
+                            });
+                        } else {
+                            // CLK high around a start condition isn't a clock pulse;
+                            // only the hold time after the start counts there
+                            violations[0] = match self.start.take() {
+                                Some(start) => self.violation(
+                                    TimingRule::StartHold,
+                                    now,
+                                    start,
+                                    self.limits.data_hold_ns,
+                                ),
+                                None => self.violation(
+                                    TimingRule::ClkHighWidth,
+                                    now,
+                                    since,
+                                    self.limits.clk_pulse_width_ns,
+                                ),
+                            };
+                            // after the checks: they belong to the clock cycle that ends now
+                            self.clk = Some((high, now));
+                        }
+                    }
+                    None => self.clk = Some((high, now)),
+                }
+            }
+            PinEventKind::WriteDio(value) => {
+                let high = value == GpioPinValue::HIGH;
+                match self.dio {
+                    Some((level, _)) if level == high => {}
+                    Some(_) => {
+                        self.dio = Some((high, now));
+                        violations[0] = self.clk_rise.and_then(|rise| {
+                            self.violation(
+                                TimingRule::DataHold,
+                                now,
+                                rise,
+                                self.limits.data_hold_ns,
+                            )
+                        });
+                        if matches!(self.clk, Some((true, _))) {
+                            if high {
+                                // stop condition
+                                self.start = None;
+                                self.in_frame = false;
+                            } else {
+                                // start condition
+                                self.start = Some(now);
+                                self.in_frame = true;
+                                self.frames += 1;
+                                self.clocks = 0;
+                            }
+                        }
+                    }
+                    None => self.dio = Some((high, now)),
+                }
+            }
+            PinEventKind::ReadDio(_) | PinEventKind::BitDelay { .. } => {}
+        }
+        violations.into_iter().flatten()
+    }
+
+    /// Returns a violation of `rule` if less than `min_ns` passed since `since`
+    /// within a frame.
+    fn violation(
+        &self,
+        rule: TimingRule,
+        now: u64,
+        since: u64,
+        min_ns: u32,
+    ) -> Option<TimingViolation> {
+        let actual_ns = now - since;
+        (self.in_frame && actual_ns < u64::from(min_ns)).then(|| TimingViolation {
+            rule,
+            position: self.position(),
+            time_ns: now,
+            actual_ns,
+            min_ns,
+        })
+    }
+
+    /// Returns the current position. While CLK is high, this is the clock
+    /// cycle of the last rising edge, otherwise the one of the next.
+    const fn position(&self) -> BusPosition {
+        let cycle = if matches!(self.clk, Some((true, _))) {
+            self.clocks.saturating_sub(1)
+        } else {
+            self.clocks
+        };
+        BusPosition {
+            frame: self.frames.saturating_sub(1),
+            byte: (cycle / 9) as u16,
+            bit: (cycle % 9) as u8,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use GpioPinValue::{HIGH, LOW};
+
+    /// Events that are `step_ns` apart from each other.
+    fn events(kinds: &[PinEventKind], step_ns: u64) -> impl Iterator<Item = PinEvent> + '_ {
+        kinds.iter().enumerate().map(move |(i, &kind)| PinEvent {
+            time_ns: i as u64 * step_ns,
+            kind,
+        })
+    }
+
+    #[test]
+    fn test_validate() {
+        use PinEventKind::{WriteClk, WriteDio};
+        // idle, start condition, 1st bit (1), 2nd bit (0)
+        let kinds = [
+            WriteClk(HIGH),
+            WriteDio(HIGH),
+            WriteDio(LOW),
+            WriteClk(LOW),
+            WriteDio(HIGH),
+            WriteClk(HIGH),
+            WriteClk(LOW),
+            WriteDio(LOW),
+            WriteClk(HIGH),
+        ];
+        let validator = TimingValidator::new(TimingLimits::DATASHEET);
+        assert_eq!(0, validator.validate(events(&kinds, 500)).count());
+
+        // 200ns are enough for setup and hold but not for the CLK pulse width
+        let mut violations = validator.validate(events(&kinds, 200));
+        assert_eq!(
+            Some(TimingViolation {
+                rule: TimingRule::ClkHighWidth,
+                position: BusPosition {
+                    frame: 0,
+                    byte: 0,
+                    bit: 0
+                },
+                time_ns: 1200,
+                actual_ns: 200,
+                min_ns: 400
+            }),
+            violations.next()
+        );
+        assert_eq!(None, violations.next());
+
+        // everything happens at once
+        let violations = validator.validate(events(&kinds, 0));
+        // the CLK high phase of the start condition only needs the hold time
+        assert!(violations.map(|v| (v.rule, v.position.bit)).eq([
+            (TimingRule::StartHold, 0),
+            (TimingRule::ClkLowWidth, 0),
+            (TimingRule::DataSetup, 0),
+            (TimingRule::ClkHighWidth, 0),
+            (TimingRule::DataHold, 1),
+            (TimingRule::ClkLowWidth, 1),
+            (TimingRule::DataSetup, 1),
+        ]));
+    }
+}
@@ -0,0 +1,219 @@
+// MIT License
+//
+// Copyright (c) 2022 Philipp Schuster <phip1611@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Records what the driver does on the pins, e.g. to debug wiring issues.
+//!
+//! [`crate::TM1637Adapter::into_recording`] wraps the GPIO functions of an
+//! adapter so that every CLK/DIO write, DIO read and bit delay is logged as a
+//! [`PinEvent`] with a timestamp into a [`Trace`]. The trace can be exported
+//! as a Value Change Dump (VCD) file, which can be viewed for example with
+//! GTKWave or PulseView.
+
+use crate::GpioPinValue;
+use crate::timing::{PinEvent, PinEventKind};
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::fmt::{Debug, Formatter, Write};
+
+/// Log of the [`PinEvent`]s of an adapter.
+pub struct Trace {
+    /// Returns the current time in nanoseconds.
+    clock_ns: Box<dyn Fn() -> u64>,
+    events: RefCell<Vec<PinEvent>>,
+}
+
+impl Debug for Trace {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Trace")
+            .field("clock_ns", &"<func>")
+            .field("events", &self.events)
+            .finish()
+    }
+}
+
+impl Trace {
+    /// Creates an empty trace that takes the timestamps from the clock.
+    /// The clock must return nanoseconds since an arbitrary point in time.
+    /// On `std`, this could be `move || start.elapsed().as_nanos() as u64`
+    /// with an `Instant` `start`.
+    pub fn new(clock_ns: impl Fn() -> u64 + 'static) -> Self {
+        Self {
+            clock_ns: Box::from(clock_ns),
+            events: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Returns a copy of the recorded events in chronological order.
+    pub fn events(&self) -> Vec<PinEvent> {
+        self.events.borrow().clone()
+    }
+
+    /// Removes all recorded events.
+    pub fn clear(&self) {
+        self.events.borrow_mut().clear();
+    }
+
+    /// Returns the current time. Timestamps never decrease, even if the
+    /// clock does.
+    pub(crate) fn now(&self) -> u64 {
+        let last = self.events.borrow().last().map_or(0, |e| e.time_ns);
+        (self.clock_ns)().max(last)
+    }
+
+    /// Records an event that happened at `time_ns`.
+    pub(crate) fn record(&self, time_ns: u64, kind: PinEventKind) {
+        self.events.borrow_mut().push(PinEvent { time_ns, kind });
+    }
+
+    /// Writes the trace in the Value Change Dump (VCD) format. The timestamps
+    /// are relative to the first event. Besides `clk` and `dio`, the dump
+    /// contains the wire `dio_read` with the value that was read last and the
+    /// wire `bit_delay` that is high while the driver waits. A read releases
+    /// DIO, so `dio` is high afterwards until the next write (the recording
+    /// doesn't know whether the TM1637 pulls it low).
+    pub fn write_vcd(&self, out: &mut impl Write) -> core::fmt::Result {
+        const CLK: char = 'c';
+        const DIO: char = 'd';
+        const DIO_READ: char = 'r';
+        const BIT_DELAY: char = 'w';
+
+        out.write_str("$timescale 1ns $end\n")?;
+        out.write_str("$scope module tm1637 $end\n")?;
+        writeln!(out, "$var wire 1 {CLK} clk $end")?;
+        writeln!(out, "$var wire 1 {DIO} dio $end")?;
+        writeln!(out, "$var wire 1 {DIO_READ} dio_read $end")?;
+        writeln!(out, "$var wire 1 {BIT_DELAY} bit_delay $end")?;
+        out.write_str("$upscope $end\n$enddefinitions $end\n")?;
+        writeln!(
+            out,
+            "$dumpvars\nx{CLK}\nx{DIO}\nx{DIO_READ}\n0{BIT_DELAY}\n$end"
+        )?;
+
+        let events = self.events.borrow();
+        let start = events.first().map_or(0, |e| e.time_ns);
+        // end of the bit delay that is in progress
+        let mut delay_end = None;
+        // level of DIO that the driver puts on the line
+        let mut dio = None;
+        let mut time = None;
+        let mut change = |out: &mut dyn Write, t: u64, id: char, value: char| {
+            if time != Some(t) {
+                time = Some(t);
+                writeln!(out, "#{}", t - start)?;
+            }
+            writeln!(out, "{value}{id}")
+        };
+
+        for event in events.iter() {
+            if let Some(end) = delay_end.filter(|&end| end <= event.time_ns) {
+                change(out, end, BIT_DELAY, '0')?;
+                delay_end = None;
+            }
+            match event.kind {
+                PinEventKind::WriteClk(value) => change(out, event.time_ns, CLK, level(value))?,
+                PinEventKind::WriteDio(value) => {
+                    dio = Some(value);
+                    change(out, event.time_ns, DIO, level(value))?;
+                }
+                PinEventKind::ReadDio(value) => {
+                    if dio != Some(GpioPinValue::HIGH) {
+                        // reading releases DIO
+                        dio = Some(GpioPinValue::HIGH);
+                        change(out, event.time_ns, DIO, level(GpioPinValue::HIGH))?;
+                    }
+                    change(out, event.time_ns, DIO_READ, level(value))?;
+                }
+                PinEventKind::BitDelay { duration_ns } => {
+                    change(out, event.time_ns, BIT_DELAY, '1')?;
+                    delay_end = Some(event.time_ns + duration_ns);
+                }
+            }
+        }
+        if let Some(end) = delay_end {
+            change(out, end, BIT_DELAY, '0')?;
+        }
+        Ok(())
+    }
+
+    /// Returns the trace in the Value Change Dump (VCD) format.
+    /// See [`Trace::write_vcd`].
+    pub fn to_vcd(&self) -> String {
+        let mut vcd = String::new();
+        self.write_vcd(&mut vcd)
+            .unwrap_or_else(|_| unreachable!("writing into a string can't fail"));
+        vcd
+    }
+}
+
+/// Returns the VCD value of the pin level.
+const fn level(value: GpioPinValue) -> char {
+    match value {
+        GpioPinValue::LOW => '0',
+        GpioPinValue::HIGH => '1',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::GpioPinValue::{HIGH, LOW};
+    use crate::TM1637Adapter;
+    use crate::timing::PinEventKind;
+    use alloc::boxed::Box;
+    use core::cell::Cell;
+
+    #[test]
+    fn test_recording() {
+        // every call of the clock advances the time by 10ns
+        let time = Cell::new(0);
+        let adapter = TM1637Adapter::new(
+            Box::from(|_| {}),
+            Box::from(|_| {}),
+            Box::from(|| LOW),
+            Box::from(|| {}),
+        );
+        let (adapter, trace) = adapter.into_recording(move || {
+            time.set(time.get() + 10);
+            time.get()
+        });
+        adapter.write_display_state();
+
+        let events = trace.events();
+        assert!(events.windows(2).all(|e| e[0].time_ns <= e[1].time_ns));
+        assert_eq!(PinEventKind::WriteDio(HIGH), events[0].kind);
+        assert_eq!(PinEventKind::WriteClk(HIGH), events[1].kind);
+        assert_eq!(PinEventKind::BitDelay { duration_ns: 10 }, events[2].kind);
+        // start condition
+        assert_eq!(PinEventKind::WriteDio(LOW), events[3].kind);
+        // ACK of the byte
+        assert!(events.iter().any(|e| e.kind == PinEventKind::ReadDio(LOW)));
+
+        let vcd = trace.to_vcd();
+        assert!(vcd.starts_with("$timescale 1ns $end\n"));
+        assert!(vcd.contains("$var wire 1 c clk $end\n"));
+        assert!(vcd.contains("\n#0\n1d\n#10\n1c\n#20\n1w\n#30\n0w\n#40\n0d\n"));
+
+        trace.clear();
+        assert!(trace.events().is_empty());
+    }
+}
@@ -0,0 +1,201 @@
+// MIT License
+//
+// Copyright (c) 2022 Philipp Schuster <phip1611@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Formatted output on the display via [`core::fmt::Write`], without `alloc`.
+//!
+//! ```
+//! # use tm1637_gpio_driver::TM1637;
+//! # fn show<P: tm1637_gpio_driver::Tm1637Pins>(display: &TM1637<P>) -> core::fmt::Result {
+//! use core::fmt::Write;
+//!
+//! let mut writer = display.writer();
+//! // shows " 23.5" on a display with decimal points; the dot doesn't take a digit
+//! writeln!(writer, "{:>4.1}", 23.46)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::board::PointStyle;
+use crate::encoding::encode_char_with_font;
+use crate::font::{AsciiFont, Font};
+use crate::mappings::{SegmentBits, SpecialCharBits};
+use crate::{DISPLAY_REGISTERS_COUNT, TM1637, TM1637Error, Tm1637Pins};
+use core::fmt;
+
+/// Buffers formatted text for the display and writes it on [`DisplayWriter::finish`]
+/// or on a newline.
+///
+/// The chars are encoded with the font of the display (see
+/// [`TM1637::with_font`]). `'.'` and `':'` are folded into
+/// the point bit of the preceding digit as described by the
+/// [`crate::board::BoardProfile`] of the display (see
+/// [`crate::encoding::encode_string_iter_with_points`]). Chars that don't fit
+/// onto the display are dropped; unused digits are blank.
+#[derive(Debug)]
+pub struct DisplayWriter<'a, P: Tm1637Pins, F: Font = AsciiFont> {
+    display: &'a TM1637<P, F>,
+    /// Encoded digits from left to right. Only the first `len` are used.
+    digits: [u8; DISPLAY_REGISTERS_COUNT],
+    len: usize,
+    /// Whether the next point can be folded into the last digit.
+    foldable: bool,
+}
+
+impl<'a, P: Tm1637Pins, F: Font> DisplayWriter<'a, P, F> {
+    /// Creates a writer with an empty buffer.
+    pub const fn new(display: &'a TM1637<P, F>) -> Self {
+        Self {
+            display,
+            digits: [SpecialCharBits::Space as u8; DISPLAY_REGISTERS_COUNT],
+            len: 0,
+            foldable: false,
+        }
+    }
+
+    /// Writes the buffered text to the display and empties the buffer.
+    pub fn finish(&mut self) -> Result<(), TM1637Error<P::Error>> {
+        let digits = self.display.board_profile().digits();
+        let res = self.display.try_write_digits(&self.digits[..digits], 0);
+        self.digits = [SpecialCharBits::Space as u8; DISPLAY_REGISTERS_COUNT];
+        self.len = 0;
+        self.foldable = false;
+        res
+    }
+
+    /// Encodes the char into the buffer.
+    fn push(&mut self, c: char) {
+        let board = self.display.board_profile();
+        if matches!(c, '.' | ':') {
+            let last = self.len.checked_sub(1);
+            match board.point_style() {
+                PointStyle::DecimalPoint if self.foldable => {
+                    if let Some(last) = last {
+                        self.digits[last] |= SegmentBits::SegPoint as u8;
+                    }
+                    self.foldable = false;
+                    return;
+                }
+                PointStyle::DecimalPoint => {}
+                PointStyle::Colon(digit) => {
+                    if self.foldable && last == Some(usize::from(digit)) {
+                        self.digits[usize::from(digit)] |= SegmentBits::SegPoint as u8;
+                    }
+                    // the colon can't be shown anywhere else
+                    self.foldable = false;
+                    return;
+                }
+            }
+            // a point of its own
+            if self.len < board.digits() {
+                self.digits[self.len] = SegmentBits::SegPoint as u8;
+                self.len += 1;
+            }
+        } else if self.len < board.digits() {
+            self.digits[self.len] = encode_char_with_font(c, self.display.font());
+            self.len += 1;
+            self.foldable = true;
+        } else {
+            // dropped; don't fold its point into another digit
+            self.foldable = false;
+        }
+    }
+}
+
+impl<P: Tm1637Pins, F: Font> fmt::Write for DisplayWriter<'_, P, F> {
+    /// Buffers the string. A newline writes the buffer to the display like
+    /// [`DisplayWriter::finish`]; transmission errors are reported as
+    /// [`fmt::Error`] then.
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            if c == '\n' {
+                self.finish().map_err(|_| fmt::Error)?;
+            } else {
+                self.push(c);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "sim"))]
+mod tests {
+    use super::*;
+    use crate::board::BoardProfile;
+    use crate::encoding::encode_digit;
+    use crate::sim::Tm1637Simulator;
+    use core::fmt::Write;
+
+    #[test]
+    fn test_write() {
+        let p = SegmentBits::SegPoint as u8;
+        let f = encode_digit;
+        let sim = Tm1637Simulator::new();
+        let mut display = TM1637::from_pins(&sim).unwrap();
+        display
+            .set_board_profile(BoardProfile::FOUR_DIGIT.with_point_style(PointStyle::DecimalPoint));
+
+        let mut writer = display.writer();
+        write!(writer, "{:>5.1}", -3.12).unwrap();
+        // nothing is written before the buffer is finished
+        assert_eq!([0; DISPLAY_REGISTERS_COUNT], sim.registers());
+        writer.finish().unwrap();
+        assert_eq!(
+            [0, SpecialCharBits::Minus as u8, f(3) | p, f(1), 0, 0],
+            sim.registers()
+        );
+
+        // a newline writes as well; the rest is blank, the overflow dropped
+        writeln!(writer, "1.2.").unwrap();
+        assert_eq!([f(1) | p, f(2) | p, 0, 0, 0, 0], sim.registers());
+        writeln!(writer, "..12345").unwrap();
+        assert_eq!([p, p, f(1), f(2), 0, 0], sim.registers());
+
+        // only the colon after digit 1 is shown
+        display.set_board_profile(BoardProfile::FOUR_DIGIT);
+        let mut writer = display.writer();
+        writeln!(writer, "1.2:{:02}", 5).unwrap();
+        assert_eq!([f(1), f(2) | p, f(0), f(5), 0, 0], sim.registers());
+    }
+
+    #[test]
+    fn test_write_with_font() {
+        use crate::font::GlyphRegistry;
+
+        let sim = Tm1637Simulator::new();
+        let mut display = TM1637::from_pins(&sim)
+            .unwrap()
+            .with_font(GlyphRegistry::<1>::new());
+        display.set_board_profile(BoardProfile::FOUR_DIGIT);
+        // registered at runtime
+        display.font_mut().insert('\u{E000}', 0x49).unwrap();
+
+        writeln!(display.writer(), "\u{E000}12").unwrap();
+        assert_eq!(
+            [0x49, encode_digit(1), encode_digit(2), 0, 0, 0],
+            sim.registers()
+        );
+
+        let mut buf = [0; 4];
+        assert_eq!(3, display.encode_text_into("1:\u{E000}0", &mut buf));
+        assert_eq!([encode_digit(1), 0x49, encode_digit(0), 0], buf);
+    }
+}